@@ -21,6 +21,18 @@ const configs = [
       }),
     ],
   }),
+  defineConfig({
+    input: { npm: "./tests/npm/mod.ts" },
+    resolve: { conditionNames: ["import"] },
+    plugins: [
+      denoLoaderPlugin({
+        importMapString: await fetch(import.meta.resolve("./deno.json"))
+          .then(
+            (r) => r.text(),
+          ),
+      }),
+    ],
+  }),
 ];
 
 for (const config of configs) {
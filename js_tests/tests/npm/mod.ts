@@ -0,0 +1,5 @@
+import nodeTypes from "npm:@types/node/package.json" with { type: "json" };
+import { useState } from "npm:preact/hooks";
+import debounce from "npm:lodash-es@4/debounce";
+
+console.log(nodeTypes.name, useState, debounce);
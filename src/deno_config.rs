@@ -0,0 +1,360 @@
+use import_map::{parse_from_json, ImportMap};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::error::DenoLoaderError;
+use crate::options::NodeModulesDirMode;
+use crate::package_json::{resolve_exports, Exports};
+
+const CONFIG_FILE_NAMES: &[&str] = &["deno.json", "deno.jsonc"];
+
+/// The fields of deno.json the plugin cares about.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DenoConfigJson {
+  /// Package name, e.g. `@acme/ui`, used to import workspace members.
+  pub name: Option<String>,
+  pub version: Option<String>,
+  /// A single entry module or a map of subpaths (`.`, `./button`) to modules.
+  pub exports: Option<Exports>,
+  pub imports: Option<Value>,
+  pub scopes: Option<Value>,
+  /// Path to a separate import map file, relative to the config file.
+  pub import_map: Option<String>,
+  pub compiler_options: Option<Value>,
+  pub node_modules_dir: Option<Value>,
+  pub unstable: Vec<String>,
+  /// Either a list of member directories or `{ "members": [...] }`.
+  pub workspace: Option<Value>,
+  /// `true`/`false`, a path, or `{ "path": ..., "frozen": ... }`.
+  pub lock: Option<Value>,
+  /// Directories of local packages that replace the jsr packages of the same name.
+  pub links: Vec<String>,
+  /// Name of `links` before deno 2.3.
+  pub patch: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DenoConfig {
+  pub path: PathBuf,
+  pub json: DenoConfigJson,
+  /// Import map in plain JSON, either from `imports`/`scopes` or from the `importMap` file.
+  pub import_map_string: String,
+  /// Url relative import map entries are resolved against.
+  pub import_map_url: url::Url,
+}
+
+/// A workspace member or linked package together with its own import map.
+#[derive(Debug, Clone)]
+pub struct WorkspaceMember {
+  pub config: DenoConfig,
+  /// `None` if the member's import map is invalid, which is reported as a configuration error.
+  pub import_map: Option<ImportMap>,
+}
+
+impl DenoConfig {
+  /// Finds `deno.json` or `deno.jsonc` in `start` or the closest ancestor. Like deno, a config
+  /// that is a member of a workspace further up resolves to the workspace root instead.
+  pub fn discover(start: &Path) -> Result<Option<Self>, DenoLoaderError> {
+    let Some(config) = Self::find(start).map(|path| Self::load(&path)).transpose()? else {
+      return Ok(None);
+    };
+    for dir in config.dir().ancestors().skip(1) {
+      let Some(path) = config_file_in(dir) else { continue };
+      let root = Self::load(&path)?;
+      if root.workspace_member_dirs()?.iter().any(|member| member == config.dir()) {
+        return Ok(Some(root));
+      }
+    }
+    Ok(Some(config))
+  }
+
+  /// Path of the config `discover` would load, without reading it.
+  pub fn find(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(config_file_in)
+  }
+
+  pub fn load(path: &Path) -> Result<Self, DenoLoaderError> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let text = read_config_file(&path)?;
+    let json: DenoConfigJson = serde_json::from_str(&strip_jsonc(&text)).map_err(|source| {
+      DenoLoaderError::InvalidConfig { path: path.display().to_string(), source }
+    })?;
+
+    let (import_map_string, import_map_path) = match &json.import_map {
+      Some(import_map) => {
+        let import_map_path = path.parent().unwrap_or(Path::new("")).join(import_map);
+        (strip_jsonc(&read_config_file(&import_map_path)?), import_map_path)
+      }
+      None => (import_map_json(json.imports.as_ref(), json.scopes.as_ref()), path.clone()),
+    };
+    let import_map_url = url::Url::from_file_path(&import_map_path)
+      .unwrap_or_else(|()| url::Url::parse("file:///").unwrap());
+
+    Ok(Self { path, json, import_map_string, import_map_url })
+  }
+
+  pub fn dir(&self) -> &Path {
+    self.path.parent().unwrap_or(Path::new(""))
+  }
+
+  pub fn node_modules_dir(&self) -> Option<NodeModulesDirMode> {
+    NodeModulesDirMode::from_value(self.json.node_modules_dir.as_ref()?)
+  }
+
+  /// The lockfile deno uses for this config, `None` when locking is disabled.
+  pub fn lock_path(&self) -> Option<PathBuf> {
+    let path = match &self.json.lock {
+      Some(Value::Bool(false)) => return None,
+      Some(Value::String(path)) => path.as_str(),
+      Some(Value::Object(lock)) => lock.get("path").and_then(Value::as_str).unwrap_or("deno.lock"),
+      _ => "deno.lock",
+    };
+    Some(self.dir().join(path))
+  }
+
+  /// Loads the deno.json of every workspace member.
+  pub fn workspace_configs(&self) -> Result<Vec<DenoConfig>, DenoLoaderError> {
+    let mut configs = Vec::new();
+    for dir in self.workspace_member_dirs()? {
+      if let Some(path) = config_file_in(&dir) {
+        configs.push(Self::load(&path)?);
+      }
+    }
+    Ok(configs)
+  }
+
+  /// Directories of the workspace members. Patterns may end in `/*` to include every directory
+  /// below a folder.
+  fn workspace_member_dirs(&self) -> Result<Vec<PathBuf>, DenoLoaderError> {
+    let mut member_dirs = Vec::new();
+    for member in self.workspace_members() {
+      let dirs = match member.strip_suffix("/*") {
+        Some(parent) => {
+          let parent = self.dir().join(parent);
+          let entries = std::fs::read_dir(&parent).map_err(|source| {
+            DenoLoaderError::ReadConfig { path: parent.display().to_string(), source }
+          })?;
+          let mut dirs = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+          dirs.sort();
+          dirs
+        }
+        None => vec![self.dir().join(&member)],
+      };
+      member_dirs.extend(dirs);
+    }
+    Ok(member_dirs)
+  }
+
+  /// Loads the deno.json of every package listed in `links` or `patch`.
+  pub fn linked_configs(&self) -> Result<Vec<DenoConfig>, DenoLoaderError> {
+    self
+      .json
+      .links
+      .iter()
+      .chain(&self.json.patch)
+      .map(|link| {
+        let dir = self.dir().join(link);
+        Self::load(&config_file_in(&dir).unwrap_or_else(|| dir.join(CONFIG_FILE_NAMES[0])))
+      })
+      .collect()
+  }
+
+  /// Resolves `subpath` (`None` for the root export) through `exports` to a file of this package.
+  pub fn resolve_export(&self, subpath: Option<&str>, conditions: &[String]) -> Option<PathBuf> {
+    let export_name = subpath.map_or_else(|| ".".to_string(), |subpath| format!("./{subpath}"));
+    let target = resolve_exports(self.json.exports.as_ref()?, &export_name, conditions)?;
+    let path = self.dir().join(target.trim_start_matches("./"));
+    path.is_file().then_some(path)
+  }
+
+  /// Workspace member directories as written in the config, e.g. `./packages/*`.
+  pub fn workspace_members(&self) -> Vec<String> {
+    let members = match &self.json.workspace {
+      Some(Value::Object(workspace)) => workspace.get("members"),
+      workspace => workspace.as_ref(),
+    };
+    match members {
+      Some(Value::Array(members)) => {
+        members.iter().filter_map(Value::as_str).map(ToString::to_string).collect()
+      }
+      _ => Vec::new(),
+    }
+  }
+}
+
+fn config_file_in(dir: &Path) -> Option<PathBuf> {
+  CONFIG_FILE_NAMES.iter().map(|name| dir.join(name)).find(|path| path.is_file())
+}
+
+fn read_config_file(path: &Path) -> Result<String, DenoLoaderError> {
+  std::fs::read_to_string(path)
+    .map_err(|source| DenoLoaderError::ReadConfig { path: path.display().to_string(), source })
+}
+
+/// Builds an import map from the `imports` and `scopes` of a config, leaving out every other key.
+pub fn import_map_json(imports: Option<&Value>, scopes: Option<&Value>) -> String {
+  let mut import_map = serde_json::Map::new();
+  if let Some(imports) = imports {
+    import_map.insert("imports".to_string(), imports.clone());
+  }
+  if let Some(scopes) = scopes {
+    import_map.insert("scopes".to_string(), scopes.clone());
+  }
+  Value::Object(import_map).to_string()
+}
+
+/// Parses an import map, or a whole deno.json of which only `imports` and `scopes` are used.
+/// Returns the map together with warnings for entries that were skipped.
+pub fn parse_import_map(
+  base_url: url::Url,
+  text: &str,
+) -> Result<(ImportMap, Vec<String>), DenoLoaderError> {
+  let invalid =
+    |message: String| DenoLoaderError::InvalidImportMap { url: base_url.to_string(), message };
+  let value: Value =
+    serde_json::from_str(&strip_jsonc(text)).map_err(|err| invalid(err.to_string()))?;
+  let json = match &value {
+    Value::Object(map) => import_map_json(map.get("imports"), map.get("scopes")),
+    _ => return Err(invalid("expected a JSON object".to_string())),
+  };
+
+  let parsed = parse_from_json(base_url.clone(), &json).map_err(|err| invalid(err.to_string()))?;
+  let warnings = parsed.diagnostics.iter().map(ToString::to_string).collect();
+  Ok((parsed.import_map, warnings))
+}
+
+/// Removes comments and trailing commas so JSONC can be read by serde_json. String contents are
+/// left untouched.
+pub fn strip_jsonc(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+  // Index in `out` of a comma that is only kept if more values follow.
+  let mut pending_comma: Option<usize> = None;
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' => {
+        pending_comma = None;
+        out.push(c);
+        while let Some(c) = chars.next() {
+          out.push(c);
+          match c {
+            '\\' => out.extend(chars.next()),
+            '"' => break,
+            _ => {}
+          }
+        }
+      }
+      '/' if chars.peek() == Some(&'/') => {
+        for c in chars.by_ref() {
+          if c == '\n' {
+            out.push('\n');
+            break;
+          }
+        }
+      }
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut prev = '\0';
+        for c in chars.by_ref() {
+          if prev == '*' && c == '/' {
+            break;
+          }
+          prev = c;
+        }
+      }
+      ',' => {
+        pending_comma = Some(out.len());
+        out.push(c);
+      }
+      '}' | ']' => {
+        if let Some(index) = pending_comma.take() {
+          out.replace_range(index..=index, " ");
+        }
+        out.push(c);
+      }
+      c if c.is_whitespace() => out.push(c),
+      c => {
+        pending_comma = None;
+        out.push(c);
+      }
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::TempDir;
+  use serde_json::json;
+
+  fn parse(text: &str) -> Value {
+    serde_json::from_str(&strip_jsonc(text)).unwrap()
+  }
+
+  #[test]
+  fn strip_comments() {
+    assert_eq!(
+      parse("// leading\n{ \"a\": 1, // line\n \"b\": /* block */ 2 /* multi\n line */ }"),
+      json!({ "a": 1, "b": 2 })
+    );
+    assert_eq!(parse("{ \"a\": /**/ 1 /* ** / */ }"), json!({ "a": 1 }));
+  }
+
+  #[test]
+  fn keep_comment_markers_in_strings() {
+    assert_eq!(
+      parse(r#"{ "url": "https://jsr.io/", "glob": "src/**/*.ts", "note": "/* kept */" }"#),
+      json!({ "url": "https://jsr.io/", "glob": "src/**/*.ts", "note": "/* kept */" })
+    );
+    assert_eq!(
+      parse(
+        r#"{ "a\"//b": "c\\" // comment
+      }"#
+      ),
+      json!({ "a\"//b": "c\\" })
+    );
+  }
+
+  #[test]
+  fn strip_trailing_commas() {
+    assert_eq!(
+      parse("{ \"imports\": [1, 2, /* c */ ], \"a\": { \"b\": 1, // c\n }, }"),
+      json!({ "imports": [1, 2], "a": { "b": 1 } })
+    );
+    // Commas inside strings and between values are kept.
+    assert_eq!(parse(r#"["a,]", "b",]"#), json!(["a,]", "b"]));
+  }
+
+  #[test]
+  fn jsonc_node_modules_dir() {
+    let config = "{\n  // installed by deno\n  \"nodeModulesDir\": \"auto\",\n}";
+    assert_eq!(NodeModulesDirMode::from_config_json(config), Some(NodeModulesDirMode::Auto));
+  }
+
+  #[test]
+  fn discover_workspace_root_from_member() {
+    let tmp = TempDir::new();
+    let root = tmp.write("deno.json", r#"{ "workspace": ["./packages/*", "./tools"] }"#);
+    let member = tmp.write("packages/a/deno.json", r#"{ "name": "@acme/a" }"#);
+    let tools = tmp.write("tools/deno.json", "{}");
+    let outside = tmp.write("scripts/deno.json", "{}");
+    tmp.write("packages/a/src/mod.ts", "");
+
+    let discover = |dir: &str| DenoConfig::discover(&tmp.path().join(dir)).unwrap().unwrap().path;
+    assert_eq!(discover("packages/a/src"), root);
+    assert_eq!(discover("tools"), root);
+    assert_eq!(discover("."), root);
+    // Configs that aren't members of the workspace above them stand alone.
+    assert_eq!(discover("scripts"), outside);
+
+    let members = DenoConfig::load(&root).unwrap().workspace_configs().unwrap();
+    assert_eq!(members.iter().map(|member| &member.path).collect::<Vec<_>>(), [&member, &tools]);
+  }
+}
@@ -0,0 +1,322 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::deno_info::{DenoMediaType, DenoResolveResult, ModuleInfo};
+use crate::options::DenoLoaderOptions;
+
+/// Deno 1.40+ appends the response metadata to the cached body instead of writing a sidecar file.
+const INLINE_METADATA_MARKER: &[u8] = b"\n// denoCacheMetadata=";
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Deserialize, Debug)]
+struct CacheMetadata {
+  #[serde(default)]
+  headers: HashMap<String, String>,
+}
+
+/// Reads remote modules straight from `$DENO_DIR/remote`, mirroring the layout of deno's http cache.
+#[derive(Debug, Clone)]
+pub struct DenoDir {
+  pub root: PathBuf,
+}
+
+impl DenoDir {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    Self { root: root.into() }
+  }
+
+  /// Uses `DENO_DIR` from the configured or process environment, then deno's platform default.
+  pub fn from_options(options: &DenoLoaderOptions) -> Option<Self> {
+    if let Some(dir) =
+      options.env.get("DENO_DIR").cloned().or_else(|| std::env::var("DENO_DIR").ok())
+    {
+      return Some(Self::new(options.working_dir().join(dir)));
+    }
+
+    let cache_home = if cfg!(target_os = "macos") {
+      std::env::var_os("HOME").map(|home| Path::new(&home).join("Library/Caches"))
+    } else if cfg!(windows) {
+      std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+      std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+    };
+    cache_home.map(|dir| Self::new(dir.join("deno")))
+  }
+
+  /// `https://deno.land:8080/std/mod.ts?x` is cached as `remote/https/deno.land_PORT8080/<sha256 of
+  /// "/std/mod.ts?x">`.
+  pub fn remote_cache_path(&self, url: &url::Url) -> Option<PathBuf> {
+    let scheme = url.scheme();
+    if scheme != "http" && scheme != "https" {
+      return None;
+    }
+    let host = match url.port() {
+      Some(port) => format!("{}_PORT{port}", url.host_str()?),
+      None => url.host_str()?.to_string(),
+    };
+    let mut rest = url.path().to_string();
+    if let Some(query) = url.query() {
+      rest.push('?');
+      rest.push_str(query);
+    }
+    let hash =
+      Sha256::digest(rest.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+    Some(self.root.join("remote").join(scheme).join(host).join(hash))
+  }
+
+  fn read_headers(&self, path: &Path) -> Option<HashMap<String, String>> {
+    let sidecar = path.with_extension("metadata.json");
+    let metadata = if let Ok(bytes) = std::fs::read(&sidecar) {
+      serde_json::from_slice::<CacheMetadata>(&bytes).ok()?
+    } else {
+      let bytes = std::fs::read(path).ok()?;
+      let start = find_inline_metadata(&bytes)? + INLINE_METADATA_MARKER.len();
+      serde_json::from_slice::<CacheMetadata>(bytes[start..].trim_ascii_end()).ok()?
+    };
+    Some(metadata.headers.into_iter().map(|(k, v)| (k.to_ascii_lowercase(), v)).collect())
+  }
+
+  /// Follows cached redirects and returns the final url, its cache file and its headers.
+  fn locate(&self, specifier: &str) -> Option<(url::Url, PathBuf, HashMap<String, String>)> {
+    let mut url = url::Url::parse(specifier).ok()?;
+    for _ in 0..MAX_REDIRECTS {
+      let path = self.remote_cache_path(&url)?;
+      let headers = self.read_headers(&path)?;
+      match headers.get("location") {
+        Some(location) => url = url.join(location).ok()?,
+        None => return Some((url, path, headers)),
+      }
+    }
+    None
+  }
+
+  /// Resolves an http(s) specifier from the cache, following cached redirects. Returns `None` on
+  /// any miss so the caller can fall back to `deno info`.
+  pub fn resolve(&self, specifier: &str) -> Option<DenoResolveResult> {
+    let (url, path, headers) = self.locate(specifier)?;
+    let local = path.to_string_lossy().into_owned();
+    let media_type = media_type_for(&url, headers.get("content-type").map(String::as_str))?;
+    Some(DenoResolveResult {
+      redirected: url.to_string(),
+      local_path: Some(local.clone()),
+      media_type: Some(media_type.clone()),
+      module: Some(ModuleInfo::esm(url.to_string(), local, media_type)),
+      importer_chain: Vec::new(),
+      has_dependencies: false,
+    })
+  }
+
+  /// Reads the body of a cached http(s) response.
+  pub fn read(&self, specifier: &str) -> Option<Vec<u8>> {
+    let (_, path, _) = self.locate(specifier)?;
+    let bytes = std::fs::read(path).ok()?;
+    Some(strip_cache_metadata(&bytes).to_vec())
+  }
+}
+
+fn find_inline_metadata(bytes: &[u8]) -> Option<usize> {
+  bytes.windows(INLINE_METADATA_MARKER.len()).rposition(|w| w == INLINE_METADATA_MARKER)
+}
+
+/// Returns the module source without deno's inline cache metadata.
+pub fn strip_cache_metadata(bytes: &[u8]) -> &[u8] {
+  match find_inline_metadata(bytes) {
+    Some(end) => &bytes[..end],
+    None => bytes,
+  }
+}
+
+/// Same precedence as deno: a known content type wins, ambiguous ones are refined by the extension.
+pub(crate) fn media_type_for(url: &url::Url, content_type: Option<&str>) -> Option<DenoMediaType> {
+  let path = url.path().to_ascii_lowercase();
+  let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_string());
+  let from_extension = || match extension.as_deref()? {
+    _ if path.ends_with(".d.ts") => Some(DenoMediaType::Dts),
+    _ if path.ends_with(".d.mts") => Some(DenoMediaType::Dmts),
+    _ if path.ends_with(".d.cts") => Some(DenoMediaType::Dcts),
+    "ts" => Some(DenoMediaType::TypeScript),
+    "mts" => Some(DenoMediaType::Mts),
+    "cts" => Some(DenoMediaType::Cts),
+    "tsx" => Some(DenoMediaType::Tsx),
+    "js" => Some(DenoMediaType::JavaScript),
+    "cjs" => Some(DenoMediaType::Cjs),
+    "mjs" => Some(DenoMediaType::Mjs),
+    "jsx" => Some(DenoMediaType::Jsx),
+    "json" => Some(DenoMediaType::Json),
+    "wasm" => Some(DenoMediaType::Wasm),
+    "map" => Some(DenoMediaType::SourceMap),
+    _ => None,
+  };
+
+  let Some(content_type) = content_type else { return from_extension() };
+  let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+  match mime.as_str() {
+    "application/typescript"
+    | "text/typescript"
+    | "video/vnd.dlna.mpeg-tts"
+    | "video/mp2t"
+    | "application/x-typescript" => match extension.as_deref() {
+      Some("tsx") => Some(DenoMediaType::Tsx),
+      Some("ts" | "mts" | "cts") => from_extension(),
+      _ => Some(DenoMediaType::TypeScript),
+    },
+    "application/javascript"
+    | "text/javascript"
+    | "application/ecmascript"
+    | "text/ecmascript"
+    | "application/x-javascript"
+    | "application/node" => match extension.as_deref() {
+      Some("jsx") => Some(DenoMediaType::Jsx),
+      Some("mjs") => Some(DenoMediaType::Mjs),
+      Some("cjs") => Some(DenoMediaType::Cjs),
+      _ => Some(DenoMediaType::JavaScript),
+    },
+    "text/jsx" => Some(DenoMediaType::Jsx),
+    "text/tsx" => Some(DenoMediaType::Tsx),
+    "application/json" | "text/json" => Some(DenoMediaType::Json),
+    "application/wasm" => Some(DenoMediaType::Wasm),
+    _ => from_extension(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::TempDir;
+
+  fn url(url: &str) -> url::Url {
+    url::Url::parse(url).unwrap()
+  }
+
+  /// Caches `body` for `specifier` like deno does, with the headers in a sidecar file or inline.
+  fn cache(dir: &DenoDir, specifier: &str, body: &str, headers: &str, inline: bool) {
+    let path = dir.remote_cache_path(&url(specifier)).unwrap();
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let metadata = format!(r#"{{"headers":{headers},"url":"{specifier}"}}"#);
+    if inline {
+      std::fs::write(&path, format!("{body}\n// denoCacheMetadata={metadata}\n")).unwrap();
+    } else {
+      std::fs::write(&path, body).unwrap();
+      std::fs::write(path.with_extension("metadata.json"), metadata).unwrap();
+    }
+  }
+
+  #[test]
+  fn remote_cache_path_layout() {
+    let dir = DenoDir::new("/deno");
+    assert_eq!(
+      dir.remote_cache_path(&url("https://example.com/")).unwrap(),
+      // sha256 of "/"
+      Path::new("/deno/remote/https/example.com")
+        .join("8a5edab282632443219e051e4ade2d1d5bbc671c781051bf1437897cbdfea0f1"),
+    );
+
+    let with_port = dir.remote_cache_path(&url("http://localhost:8080/mod.ts")).unwrap();
+    assert!(with_port.starts_with("/deno/remote/http/localhost_PORT8080"));
+
+    let plain = dir.remote_cache_path(&url("https://example.com/mod.ts")).unwrap();
+    let with_query =
+      dir.remote_cache_path(&url("https://example.com/mod.ts?target=es2022")).unwrap();
+    assert_ne!(plain, with_query);
+    assert_eq!(plain.parent(), with_query.parent());
+
+    assert_eq!(dir.remote_cache_path(&url("file:///mod.ts")), None);
+  }
+
+  #[test]
+  fn resolve_with_sidecar_metadata() {
+    let tmp = TempDir::new();
+    let dir = DenoDir::new(tmp.path());
+    cache(
+      &dir,
+      "https://example.com/mod",
+      "export const a = 1;",
+      r#"{"Content-Type":"application/typescript; charset=utf-8"}"#,
+      false,
+    );
+
+    let result = dir.resolve("https://example.com/mod").unwrap();
+    assert_eq!(result.redirected, "https://example.com/mod");
+    assert_eq!(result.media_type, Some(DenoMediaType::TypeScript));
+    assert_eq!(dir.read("https://example.com/mod").unwrap(), b"export const a = 1;");
+  }
+
+  #[test]
+  fn resolve_with_inline_metadata() {
+    let tmp = TempDir::new();
+    let dir = DenoDir::new(tmp.path());
+    cache(
+      &dir,
+      "https://example.com/mod.js",
+      "export const a = 1;",
+      r#"{"content-type":"application/javascript"}"#,
+      true,
+    );
+
+    let result = dir.resolve("https://example.com/mod.js").unwrap();
+    assert_eq!(result.media_type, Some(DenoMediaType::JavaScript));
+    assert_eq!(dir.read("https://example.com/mod.js").unwrap(), b"export const a = 1;");
+  }
+
+  #[test]
+  fn resolve_follows_redirects() {
+    let tmp = TempDir::new();
+    let dir = DenoDir::new(tmp.path());
+    cache(&dir, "https://example.com/latest/mod.ts", "", r#"{"location":"/v2/mod.ts"}"#, false);
+    cache(&dir, "https://example.com/v2/mod.ts", "export {};", "{}", true);
+
+    let result = dir.resolve("https://example.com/latest/mod.ts").unwrap();
+    assert_eq!(result.redirected, "https://example.com/v2/mod.ts");
+    assert_eq!(result.media_type, Some(DenoMediaType::TypeScript));
+    assert_eq!(
+      result.local_path.as_deref(),
+      dir.remote_cache_path(&url("https://example.com/v2/mod.ts")).unwrap().to_str(),
+    );
+  }
+
+  #[test]
+  fn resolve_misses_uncached_modules() {
+    let tmp = TempDir::new();
+    let dir = DenoDir::new(tmp.path());
+    cache(&dir, "https://example.com/a.ts", "", r#"{"location":"./b.ts"}"#, false);
+
+    assert!(dir.resolve("https://example.com/missing.ts").is_none());
+    assert!(dir.resolve("https://example.com/a.ts").is_none());
+  }
+
+  #[test]
+  fn strip_cache_metadata_keeps_plain_sources() {
+    assert_eq!(strip_cache_metadata(b"export {};"), b"export {};");
+    assert_eq!(strip_cache_metadata(b"export {};\n// denoCacheMetadata={}"), b"export {};");
+  }
+
+  #[test]
+  fn media_type_precedence() {
+    let media_type = |path: &str, content_type: Option<&str>| {
+      media_type_for(&url(&format!("https://example.com{path}")), content_type)
+    };
+    assert_eq!(media_type("/mod.ts", None), Some(DenoMediaType::TypeScript));
+    assert_eq!(media_type("/types.d.ts", None), Some(DenoMediaType::Dts));
+    assert_eq!(media_type("/MOD.MJS", None), Some(DenoMediaType::Mjs));
+    assert_eq!(media_type("/mod", None), None);
+    // A typescript content type is refined by the extension.
+    assert_eq!(media_type("/app.tsx", Some("application/typescript")), Some(DenoMediaType::Tsx));
+    assert_eq!(media_type("/mod.mts", Some("text/typescript")), Some(DenoMediaType::Mts));
+    assert_eq!(media_type("/mod", Some("application/typescript")), Some(DenoMediaType::TypeScript));
+    // A known content type wins over the extension.
+    assert_eq!(
+      media_type("/mod.ts", Some("application/javascript")),
+      Some(DenoMediaType::JavaScript)
+    );
+    assert_eq!(
+      media_type("/data", Some("application/json; charset=utf-8")),
+      Some(DenoMediaType::Json)
+    );
+    // Unknown content types fall back to the extension.
+    assert_eq!(media_type("/mod.jsx", Some("text/plain")), Some(DenoMediaType::Jsx));
+  }
+}
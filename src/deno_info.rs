@@ -0,0 +1,420 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+use rolldown_common::ModuleType;
+
+use crate::error::DenoLoaderError;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind")]
+pub enum ModuleInfo {
+  #[serde(rename = "esm")]
+  Esm {
+    local: Option<String>,
+    specifier: String,
+    #[serde(rename = "mediaType")]
+    media_type: DenoMediaType,
+    #[serde(default)]
+    dependencies: Vec<DependencyInfo>,
+    #[serde(rename = "typesDependency", default)]
+    types_dependency: Option<TypesDependencyInfo>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    checksum: Option<String>,
+    #[serde(default)]
+    emit: Option<String>,
+    #[serde(default)]
+    map: Option<String>,
+  },
+  #[serde(rename = "wasm")]
+  Wasm {
+    local: Option<String>,
+    specifier: String,
+    #[serde(rename = "mediaType")]
+    media_type: DenoMediaType,
+    #[serde(default)]
+    dependencies: Vec<DependencyInfo>,
+    #[serde(default)]
+    size: Option<u64>,
+  },
+  #[serde(rename = "npm")]
+  Npm {
+    specifier: String,
+    #[serde(rename = "npmPackage")]
+    npm_package: String,
+  },
+  #[serde(rename = "node")]
+  Node {
+    specifier: String,
+    #[serde(rename = "moduleName", default)]
+    module_name: Option<String>,
+  },
+  #[serde(rename = "external")]
+  External { specifier: String },
+  /// A module deno failed to fetch or parse. deno reports these without a `kind`.
+  #[serde(rename = "error")]
+  Error { specifier: String, error: String },
+  /// A module kind this plugin doesn't know about, e.g. one added by a newer deno release.
+  #[serde(rename = "unknown")]
+  Unknown { specifier: String },
+}
+
+impl ModuleInfo {
+  pub fn esm(specifier: String, local: String, media_type: DenoMediaType) -> Self {
+    Self::Esm {
+      local: Some(local),
+      specifier,
+      media_type,
+      dependencies: Vec::new(),
+      types_dependency: None,
+      size: None,
+      checksum: None,
+      emit: None,
+      map: None,
+    }
+  }
+
+  pub fn specifier(&self) -> &str {
+    match self {
+      Self::Esm { specifier, .. }
+      | Self::Wasm { specifier, .. }
+      | Self::Npm { specifier, .. }
+      | Self::Node { specifier, .. }
+      | Self::External { specifier }
+      | Self::Error { specifier, .. }
+      | Self::Unknown { specifier } => specifier,
+    }
+  }
+
+  /// Never fails, entries that don't match the schema become `Error` or `Unknown`.
+  fn from_value(mut value: serde_json::Value) -> Self {
+    if value.get("error").is_some_and(serde_json::Value::is_string) {
+      value["kind"] = "error".into();
+    }
+    let specifier = value.get("specifier").and_then(serde_json::Value::as_str).unwrap_or_default();
+    let specifier = specifier.to_string();
+    serde_json::from_value(value).unwrap_or(Self::Unknown { specifier })
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyInfo {
+  /// The specifier as written in the importing module.
+  pub specifier: String,
+  #[serde(default)]
+  pub code: Option<ResolvedDependencyInfo>,
+  #[serde(default, rename = "type")]
+  pub types: Option<ResolvedDependencyInfo>,
+  #[serde(default)]
+  pub is_dynamic: bool,
+  #[serde(default)]
+  pub assertion_type: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TypesDependencyInfo {
+  pub specifier: String,
+  pub dependency: ResolvedDependencyInfo,
+}
+
+/// Either the resolved `specifier` or the resolution `error`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ResolvedDependencyInfo {
+  #[serde(default)]
+  pub specifier: Option<String>,
+  #[serde(default)]
+  pub error: Option<String>,
+  #[serde(default)]
+  pub span: Option<Span>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Span {
+  pub start: Position,
+  pub end: Position,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Position {
+  pub line: u32,
+  pub character: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum DenoMediaType {
+  JavaScript,
+  Jsx,
+  Mjs,
+  Cjs,
+  TypeScript,
+  Mts,
+  Cts,
+  Dts,
+  Dmts,
+  Dcts,
+  Tsx,
+  Json,
+  Wasm,
+  SourceMap,
+  #[serde(other)]
+  Unknown,
+}
+
+impl DenoMediaType {
+  pub fn module_type(&self) -> ModuleType {
+    match self {
+      Self::JavaScript | Self::Mjs | Self::Cjs => ModuleType::Js,
+      Self::Jsx => ModuleType::Jsx,
+      Self::TypeScript | Self::Mts | Self::Cts | Self::Dts | Self::Dmts | Self::Dcts => {
+        ModuleType::Ts
+      }
+      Self::Json | Self::SourceMap => ModuleType::Json,
+      Self::Wasm => ModuleType::Binary,
+      // Tsx is the most permissive syntax, which makes it the safest guess.
+      Self::Tsx | Self::Unknown => ModuleType::Tsx,
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NpmPackageInfo {
+  pub name: String,
+  pub version: String,
+  /// Package ids, e.g. `loose-envify@1.4.0`, that are keys of `npmPackages` as well.
+  #[serde(default)]
+  pub dependencies: Vec<String>,
+}
+
+/// Output of `deno info --json`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DenoInfoJsonV1 {
+  pub version: u32,
+  pub roots: Vec<String>,
+  pub redirects: HashMap<String, String>,
+  #[serde(deserialize_with = "deserialize_modules")]
+  pub modules: Vec<ModuleInfo>,
+  pub npm_packages: HashMap<String, NpmPackageInfo>,
+}
+
+fn deserialize_modules<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<Vec<ModuleInfo>, D::Error> {
+  let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+  Ok(values.into_iter().map(ModuleInfo::from_value).collect())
+}
+
+/// What a specifier resolved to within a deno info graph.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub(crate) struct DenoResolveResult {
+  pub redirected: String,
+  pub local_path: Option<String>,
+  pub media_type: Option<DenoMediaType>,
+  /// The graph entry for `redirected`, if deno reported one.
+  pub module: Option<ModuleInfo>,
+  /// Modules leading from a graph root to `redirected`, closest importer first. Only collected
+  /// when deno reported an error for the module.
+  #[serde(default)]
+  pub importer_chain: Vec<String>,
+  /// Whether `module` lists the dependencies of the module. Results read from `$DENO_DIR` or a jsr
+  /// registry without asking deno don't.
+  #[serde(default)]
+  pub has_dependencies: bool,
+}
+
+impl DenoResolveResult {
+  pub fn from_graph(specifier: &str, info: &DenoInfoJsonV1) -> Result<Self, DenoLoaderError> {
+    let redirected = follow_redirects(specifier, &info.redirects)?;
+    let module = info.modules.iter().find(|m| m.specifier() == redirected).cloned();
+    let (local_path, media_type) = match &module {
+      Some(
+        ModuleInfo::Esm { local, media_type, .. } | ModuleInfo::Wasm { local, media_type, .. },
+      ) => (local.clone(), Some(media_type.clone())),
+      _ => (None, None),
+    };
+
+    let importer_chain = match &module {
+      Some(ModuleInfo::Error { .. }) => importer_chain(info, &redirected),
+      _ => Vec::new(),
+    };
+
+    Ok(Self { redirected, local_path, media_type, module, importer_chain, has_dependencies: true })
+  }
+
+  /// Turns an error deno recorded for the module into a `DenoLoaderError`.
+  pub fn ensure_loaded(self) -> Result<Self, DenoLoaderError> {
+    match &self.module {
+      Some(ModuleInfo::Error { specifier, error }) => Err(DenoLoaderError::ModuleError {
+        specifier: specifier.clone(),
+        error: error.clone(),
+        importer_chain: self.importer_chain.clone(),
+      }),
+      _ => Ok(self),
+    }
+  }
+
+  pub fn is_error(&self) -> bool {
+    matches!(self.module, Some(ModuleInfo::Error { .. }))
+  }
+}
+
+/// Walks the dependency edges of `info` backwards from `specifier` towards a root.
+fn importer_chain(info: &DenoInfoJsonV1, specifier: &str) -> Vec<String> {
+  let importer_of = |target: &str| {
+    info.modules.iter().find_map(|m| {
+      let ModuleInfo::Esm { specifier: importer, dependencies, .. } = m else { return None };
+      dependencies
+        .iter()
+        .filter_map(|dep| dep.code.as_ref()?.specifier.as_deref())
+        .any(|resolved| {
+          follow_redirects(resolved, &info.redirects).is_ok_and(|resolved| resolved == target)
+        })
+        .then(|| importer.clone())
+    })
+  };
+
+  let mut chain: Vec<String> = Vec::new();
+  let mut current = specifier.to_string();
+  while let Some(importer) = importer_of(&current) {
+    if importer == specifier || chain.contains(&importer) {
+      break;
+    }
+    chain.push(importer.clone());
+    current = importer;
+  }
+  chain
+}
+
+pub fn follow_redirects(
+  initial: &str,
+  redirects: &HashMap<String, String>,
+) -> Result<String, DenoLoaderError> {
+  let mut current = initial.to_string();
+  let mut seen = std::collections::HashSet::new();
+
+  while let Some(next) = redirects.get(&current) {
+    if !seen.insert(current.clone()) {
+      return Err(DenoLoaderError::CircularRedirect { specifier: initial.to_string() });
+    }
+    current = next.clone();
+  }
+
+  Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn info_json_tolerates_unknown_entries() {
+    let info: DenoInfoJsonV1 = serde_json::from_value(serde_json::json!({
+      "version": 1,
+      "roots": ["file:///app/main.ts"],
+      "redirects": {},
+      "modules": [
+        {
+          "kind": "esm",
+          "specifier": "file:///app/main.ts",
+          "local": "/app/main.ts",
+          "mediaType": "TypeScript",
+          "dependencies": [{ "specifier": "npm:react@18" }]
+        },
+        {
+          "kind": "esm",
+          "specifier": "file:///app/styles.css",
+          "local": "/app/styles.css",
+          "mediaType": "Css"
+        },
+        { "kind": "asset", "specifier": "file:///app/logo.svg" },
+        { "specifier": "https://example.com/gone.ts", "error": "Module not found" },
+        {
+          "kind": "wasm",
+          "specifier": "file:///app/add.wasm",
+          "local": "/app/add.wasm",
+          "mediaType": "Wasm"
+        },
+        { "kind": "npm", "specifier": "npm:/react@18.3.1", "npmPackage": "react@18.3.1" }
+      ],
+      "npmPackages": {
+        "react@18.3.1": {
+          "name": "react",
+          "version": "18.3.1",
+          "dependencies": ["loose-envify@1.4.0"]
+        },
+        "loose-envify@1.4.0": { "name": "loose-envify", "version": "1.4.0" }
+      }
+    }))
+    .unwrap();
+
+    assert_eq!(info.roots, ["file:///app/main.ts"]);
+    assert!(
+      matches!(&info.modules[0], ModuleInfo::Esm { dependencies, .. } if dependencies.len() == 1)
+    );
+    assert!(matches!(&info.modules[1], ModuleInfo::Esm { media_type: DenoMediaType::Unknown, .. }));
+    assert!(matches!(
+      &info.modules[2],
+      ModuleInfo::Unknown { specifier } if specifier == "file:///app/logo.svg"
+    ));
+    assert!(matches!(
+      &info.modules[3],
+      ModuleInfo::Error { error, .. } if error == "Module not found"
+    ));
+    assert!(matches!(&info.modules[4], ModuleInfo::Wasm { media_type: DenoMediaType::Wasm, .. }));
+    assert!(matches!(
+      &info.modules[5],
+      ModuleInfo::Npm { npm_package, .. } if npm_package == "react@18.3.1"
+    ));
+    assert_eq!(info.npm_packages["react@18.3.1"].dependencies, ["loose-envify@1.4.0"]);
+    assert!(info.npm_packages["loose-envify@1.4.0"].dependencies.is_empty());
+  }
+  #[test]
+  fn module_errors_name_the_importer_chain() {
+    let info: DenoInfoJsonV1 = serde_json::from_value(serde_json::json!({
+      "roots": ["file:///app/main.ts"],
+      "redirects": { "https://example.com/lib.ts": "https://example.com/lib@1.0.0.ts" },
+      "modules": [
+        {
+          "kind": "esm",
+          "specifier": "file:///app/main.ts",
+          "local": "/app/main.ts",
+          "mediaType": "TypeScript",
+          "dependencies": [{
+            "specifier": "https://example.com/lib.ts",
+            "code": { "specifier": "https://example.com/lib.ts" }
+          }]
+        },
+        {
+          "kind": "esm",
+          "specifier": "https://example.com/lib@1.0.0.ts",
+          "local": "/deno/lib.ts",
+          "mediaType": "TypeScript",
+          "dependencies": [{
+            "specifier": "./missing.ts",
+            "code": { "specifier": "https://example.com/missing.ts" }
+          }]
+        },
+        { "specifier": "https://example.com/missing.ts", "error": "Module not found" }
+      ]
+    }))
+    .unwrap();
+
+    let result = DenoResolveResult::from_graph("https://example.com/missing.ts", &info).unwrap();
+    assert!(result.is_error());
+    assert_eq!(result.importer_chain, ["https://example.com/lib@1.0.0.ts", "file:///app/main.ts"]);
+    assert_eq!(
+      result.ensure_loaded().unwrap_err().to_string(),
+      concat!(
+        "deno could not load \"https://example.com/missing.ts\": Module not found\n",
+        "    imported from https://example.com/lib@1.0.0.ts\n",
+        "    imported from file:///app/main.ts",
+      )
+    );
+
+    let root = DenoResolveResult::from_graph("file:///app/main.ts", &info).unwrap();
+    assert!(root.importer_chain.is_empty());
+    assert!(root.ensure_loaded().is_ok());
+  }
+}
@@ -1,122 +1,568 @@
+use dashmap::DashMap;
+use futures::future::join_all;
 use rolldown_fs::{FileSystem, OsFileSystem};
-use serde::Deserialize;
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::OnceCell;
 
-use rolldown_common::ModuleType;
+use rolldown_common::{LogWithoutPlugin, ModuleType};
 use rolldown_plugin::{
-  HookLoadArgs, HookLoadOutput, HookLoadReturn, HookResolveIdArgs, HookResolveIdOutput,
-  HookResolveIdReturn, Plugin, PluginContext, PluginContextResolveOptions,
+  HookBuildStartArgs, HookLoadArgs, HookLoadOutput, HookLoadReturn, HookNoopReturn,
+  HookResolveIdArgs, HookResolveIdOutput, HookResolveIdReturn, Plugin, PluginContext,
+  PluginContextResolveOptions,
 };
 
-use import_map::parse_from_json;
-
-#[derive(Deserialize, Debug, Clone)]
-#[serde(tag = "kind")]
-enum ModuleInfo {
-  #[serde(rename = "esm")]
-  Esm {
-    local: String,
-    specifier: String,
-    #[serde(rename = "mediaType")]
-    media_type: DenoMediaType,
-  },
-  #[serde(rename = "npm")]
-  Npm {
-    specifier: String,
-    #[serde(rename = "npmPackage")]
-    npm_package: String,
-  },
-}
+use import_map::ImportMap;
+
+use crate::deno_config::{parse_import_map, DenoConfig, WorkspaceMember};
+use crate::deno_dir::{strip_cache_metadata, DenoDir};
+use crate::deno_info::{
+  DenoInfoJsonV1, DenoMediaType, DenoResolveResult, ModuleInfo, NpmPackageInfo,
+};
+use crate::disk_cache::DiskCache;
+use crate::error::DenoLoaderError;
+use crate::external::ExternalPolicy;
+use crate::jsr::{JsrResolver, JsrSpecifier};
+use crate::lockfile::Lockfile;
+use crate::npm::NpmSpecifier;
+use crate::npm_cache::NpmCache;
+use crate::options::{DenoLoaderOptions, ExternalSpecifier, NodeModulesDirMode};
+use crate::package_json::{platform_conditions, PackageJson, DEFAULT_CONDITIONS};
+use crate::provider::{DenoInfoProvider, DenoSubprocessProvider};
 
-#[derive(Deserialize, Debug, Clone)]
-enum DenoMediaType {
-  TypeScript,
-  Tsx,
-  JavaScript,
-  Jsx,
-  Json,
-  Dmts,
-  Mjs,
+#[derive(Debug)]
+pub struct DenoLoaderPlugin {
+  /// Concurrent lookups of the same specifier share one cell, so only the first caller runs the
+  /// provider and the others await its result.
+  resolve_cache: DashMap<String, Arc<OnceCell<DenoResolveResult>>>,
+  /// Results from deno info for modules whose `resolve_cache` entry was read without deno and so
+  /// doesn't know their imports.
+  graph_cache: DashMap<String, Arc<OnceCell<DenoResolveResult>>>,
+  disk_cache: OnceCell<Option<DiskCache>>,
+  deno_dir: Option<DenoDir>,
+  jsr_resolver: Option<JsrResolver>,
+  npm_cache: Option<NpmCache>,
+  /// `npmPackages` of every graph seen so far, keyed by deno's package id.
+  npm_packages: DashMap<String, NpmPackageInfo>,
+  /// Conditions for package `exports`, replaced by rolldown's `conditionNames` or the defaults of
+  /// its `platform` in `build_start` unless `options.conditions` is set.
+  conditions: Mutex<Vec<String>>,
+  node_modules_dir: NodeModulesDirMode,
+  lockfile_path: Option<PathBuf>,
+  lockfile: Option<Lockfile>,
+  pub import_map_string: String,
+  /// The map parsed once from `import_map_string`, `None` if it is invalid. Targets are relative to
+  /// the config or import map file when one was found, otherwise to `options.cwd` or, when that is
+  /// unset too, to rolldown's cwd once `build_start` runs.
+  import_map: OnceLock<Option<ImportMap>>,
+  /// deno.json loaded from `options.config` or discovered from `options.cwd`.
+  pub config: Option<DenoConfig>,
+  /// Workspace members and packages linked with `links`, which take precedence over jsr.
+  workspace: Vec<WorkspaceMember>,
+  external: ExternalPolicy,
+  /// Reported from every `build_start`, as constructing the plugin can't fail.
+  config_error: Mutex<Option<Arc<DenoLoaderError>>>,
+  config_warnings: Mutex<Vec<String>>,
+  pub options: DenoLoaderOptions,
+  provider: Arc<dyn DenoInfoProvider>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct DenoInfoJsonV1 {
-  redirects: HashMap<String, String>,
-  modules: Vec<ModuleInfo>,
+impl Default for DenoLoaderPlugin {
+  fn default() -> Self {
+    Self::new(r#"{}"#.to_string())
+  }
 }
 
-fn follow_redirects(
-  initial: &str,
-  redirects: &HashMap<String, String>,
-) -> Result<String, &'static str> {
-  let mut current = initial.to_string();
-  let mut seen = std::collections::HashSet::new();
+impl DenoLoaderPlugin {
+  pub fn new(import_map_string: String) -> Self {
+    Self::with_options(DenoLoaderOptions {
+      import_map_string: Some(import_map_string),
+      ..Default::default()
+    })
+  }
+
+  pub fn with_options(options: DenoLoaderOptions) -> Self {
+    let config = match (&options.import_map_string, &options.config) {
+      (Some(_), _) => Ok(None),
+      (None, Some(path)) => DenoConfig::load(&options.working_dir().join(path)).map(Some),
+      (None, None) => DenoConfig::discover(&options.working_dir()),
+    };
+    let (config, mut config_error) = match config {
+      Ok(config) => (config, None),
+      Err(err) => (None, Some(err)),
+    };
 
-  while let Some(next) = redirects.get(&current) {
-    if !seen.insert(current.clone()) {
-      return Err("Circular redirect detected");
+    let import_map_string = options
+      .import_map_string
+      .clone()
+      .or_else(|| Some(config.as_ref()?.import_map_string.clone()))
+      .unwrap_or_else(|| r#"{}"#.to_string());
+    let import_map_url = match &config {
+      Some(config) => Some(config.import_map_url.clone()),
+      None => options.cwd.as_ref().map(|_| directory_url(&options.working_dir())),
+    };
+    let workspace_configs = match config.as_ref().map(|config| {
+      let mut configs = config.workspace_configs()?;
+      configs.extend(config.linked_configs()?);
+      Ok::<_, DenoLoaderError>(configs)
+    }) {
+      Some(Ok(configs)) => configs,
+      Some(Err(err)) => {
+        config_error.get_or_insert(err);
+        Vec::new()
+      }
+      None => Vec::new(),
+    };
+    let external = ExternalPolicy::new(&options.external).unwrap_or_else(|err| {
+      config_error.get_or_insert(err);
+      ExternalPolicy::default()
+    });
+    let mut config_warnings = Vec::new();
+    let mut parse = |url: &url::Url, text: &str| match parse_import_map(url.clone(), text) {
+      Ok((import_map, warnings)) => {
+        config_warnings.extend(warnings);
+        Some(import_map)
+      }
+      Err(err) => {
+        config_error.get_or_insert(err);
+        None
+      }
+    };
+    let import_map = match import_map_url {
+      Some(url) => OnceLock::from(parse(&url, &import_map_string)),
+      None => OnceLock::new(),
+    };
+    let workspace = workspace_configs
+      .into_iter()
+      .map(|config| WorkspaceMember {
+        import_map: parse(&config.import_map_url, &config.import_map_string),
+        config,
+      })
+      .collect();
+    let lockfile_path = match &config {
+      Some(config) => config.lock_path(),
+      None => Some(options.working_dir().join("deno.lock")),
+    };
+
+    let lockfile = lockfile_path.as_deref().and_then(Lockfile::load);
+    let deno_dir = options.read_deno_dir.then(|| DenoDir::from_options(&options)).flatten();
+    let jsr_resolver = options
+      .native_jsr_resolution
+      .then(|| {
+        JsrResolver::new(options.jsr_registry.as_deref(), deno_dir.clone(), lockfile.clone())
+      })
+      .flatten();
+    // npm packages are read from the global cache whether or not `read_deno_dir` is set, it only
+    // controls looking up remote modules without asking deno.
+    let npm_cache =
+      DenoDir::from_options(&options).and_then(|deno_dir| NpmCache::new(&deno_dir, &options));
+    Self {
+      resolve_cache: DashMap::new(),
+      graph_cache: DashMap::new(),
+      disk_cache: OnceCell::new(),
+      deno_dir,
+      jsr_resolver,
+      npm_cache,
+      npm_packages: DashMap::new(),
+      conditions: Mutex::new(
+        options
+          .conditions
+          .clone()
+          .unwrap_or_else(|| DEFAULT_CONDITIONS.iter().map(ToString::to_string).collect()),
+      ),
+      node_modules_dir: options
+        .node_modules_dir
+        .or_else(|| config.as_ref()?.node_modules_dir())
+        .or_else(|| NodeModulesDirMode::from_config_json(options.import_map_string.as_deref()?))
+        .unwrap_or_else(|| {
+          NodeModulesDirMode::default_for(
+            config.as_ref().map_or(options.working_dir().as_path(), DenoConfig::dir),
+          )
+        }),
+      lockfile_path,
+      lockfile,
+      import_map_string,
+      import_map,
+      config,
+      workspace,
+      external,
+      config_error: Mutex::new(config_error.map(Arc::new)),
+      config_warnings: Mutex::new(config_warnings),
+      provider: Arc::new(DenoSubprocessProvider::new(options.clone())),
+      options,
     }
-    current = next.clone();
   }
 
-  Ok(current)
-}
+  /// Replaces the default `deno info` subprocess with another graph source.
+  pub fn with_provider(mut self, provider: impl DenoInfoProvider + 'static) -> Self {
+    self.provider = Arc::new(provider);
+    self
+  }
 
-fn get_deno_info(specifier: &str) -> Result<DenoInfoJsonV1, &'static str> {
-  let output = std::process::Command::new("deno")
-    .args(["info", "--json", specifier])
-    .output()
-    .expect("Failed to execute deno info command");
+  async fn get_cached_info(&self, specifier: &str) -> Result<DenoResolveResult, DenoLoaderError> {
+    let cell = self.resolve_cache.entry(specifier.to_string()).or_default().clone();
 
-  if !output.status.success() {
-    return Err("deno info command failed");
+    cell
+      .get_or_try_init(|| async {
+        let disk_cache = self.disk_cache().await;
+        if let Some(cached) = disk_cache.and_then(|cache| cache.get(specifier)) {
+          return Ok(cached);
+        }
+        if specifier.starts_with("jsr:") {
+          if let Some(cached) =
+            self.jsr_resolver.as_ref().and_then(|jsr| jsr.resolve(specifier, &self.conditions()))
+          {
+            return Ok(cached);
+          }
+        } else if specifier.starts_with("http:") || specifier.starts_with("https:") {
+          if let Some(cached) = self.deno_dir.as_ref().and_then(|dir| dir.resolve(specifier)) {
+            return Ok(cached);
+          }
+        }
+
+        self.info_from_provider(specifier).await
+      })
+      .await
+      .cloned()?
+      .ensure_loaded()
   }
 
-  Ok(serde_json::from_slice(&output.stdout).expect("Failed to parse JSON output"))
-}
+  /// Asks the provider for the graph of `specifier`.
+  async fn info_from_provider(
+    &self,
+    specifier: &str,
+  ) -> Result<DenoResolveResult, DenoLoaderError> {
+    let info = self.provider.info(specifier).await?;
+    self.register_npm_packages(&info);
+    if self.options.prefetch_graph {
+      self.seed_from_graph(&info).await;
+    }
+    let result = DenoResolveResult::from_graph(specifier, &info)?;
+    // Fetch errors may be transient, don't persist them.
+    if let Some(cache) = self.disk_cache().await.filter(|_| !result.is_error()) {
+      // The disk cache is best effort, a failed write only costs a lookup in the next build.
+      let _ = cache.set(specifier, &result);
+    }
+    Ok(result)
+  }
 
-#[derive(Debug, Clone)]
-struct DenoResolveResult {
-  info: DenoInfoJsonV1,
-  local_path: Option<String>,
-  redirected: String,
-}
+  /// Like `get_cached_info`, but makes sure the result lists the module's dependencies.
+  async fn get_graph_info(&self, specifier: &str) -> Result<DenoResolveResult, DenoLoaderError> {
+    let cached = self.get_cached_info(specifier).await?;
+    if cached.has_dependencies {
+      return Ok(cached);
+    }
+    let cell = self.graph_cache.entry(specifier.to_string()).or_default().clone();
+    cell.get_or_try_init(|| self.info_from_provider(specifier)).await.cloned()?.ensure_loaded()
+  }
 
-#[derive(Debug)]
-pub struct DenoLoaderPlugin {
-  resolve_cache: Mutex<HashMap<String, DenoResolveResult>>,
-  pub import_map_string: String,
-}
+  fn import_map(&self) -> Option<&ImportMap> {
+    self.import_map.get()?.as_ref()
+  }
 
-impl Default for DenoLoaderPlugin {
-  fn default() -> Self {
-    Self::new(r#"{}"#.to_string())
+  /// Maps `specifier` through the import map of the workspace member `importer` belongs to, then
+  /// through the root import map. Like deno, specifiers are resolved against the importer, so
+  /// `scopes` entries match it, and against `cwd` for entries without one. `None` unless an entry
+  /// of a map matched.
+  fn map_import(&self, specifier: &str, importer: Option<&str>, cwd: &Path) -> Option<url::Url> {
+    let referrer = importer.and_then(importer_url).unwrap_or_else(|| directory_url(cwd));
+    // What the import maps return when no entry matches, bare specifiers fail instead.
+    let is_relative = ["./", "../", "/"].iter().any(|prefix| specifier.starts_with(prefix));
+    let unmapped =
+      if is_relative { referrer.join(specifier).ok() } else { url::Url::parse(specifier).ok() };
+    let member_import_map = importer
+      .and_then(|importer| self.workspace_member_of(importer))
+      .and_then(|member| member.import_map.as_ref());
+    member_import_map
+      .into_iter()
+      .chain(self.import_map())
+      .filter_map(|import_map| import_map.resolve(specifier, &referrer).ok())
+      .find(|url| Some(url) != unmapped.as_ref())
   }
-}
 
-impl DenoLoaderPlugin {
-  pub fn new(import_map_string: String) -> Self {
-    Self { resolve_cache: Mutex::new(HashMap::new()), import_map_string }
+  fn conditions(&self) -> Vec<String> {
+    self.conditions.lock().unwrap().clone()
   }
 
-  fn get_cached_info(&self, specifier: &str) -> Result<DenoResolveResult, &'static str> {
-    if let Some(cached) = self.resolve_cache.lock().unwrap().get(specifier).cloned() {
-      return Ok(cached);
+  /// The workspace member whose directory contains the local module `importer`.
+  fn workspace_member_of(&self, importer: &str) -> Option<&WorkspaceMember> {
+    let importer = Path::new(importer);
+    self
+      .workspace
+      .iter()
+      .filter(|member| importer.starts_with(member.config.dir()))
+      .max_by_key(|member| member.config.dir().as_os_str().len())
+  }
+
+  /// Resolves `@scope/name/subpath` or `jsr:@scope/name@version/subpath` to a file of the workspace
+  /// member or linked package with that name.
+  fn resolve_workspace_member(&self, specifier: &str) -> Option<String> {
+    if self.workspace.is_empty() {
+      return None;
     }
+    let parsed = if specifier.starts_with("jsr:") {
+      JsrSpecifier::parse(specifier)?
+    } else if specifier.starts_with('@') {
+      JsrSpecifier::parse(&format!("jsr:{specifier}"))?
+    } else {
+      return None;
+    };
+    let name = parsed.package_name();
+    let member =
+      self.workspace.iter().find(|member| member.config.json.name.as_ref() == Some(&name))?;
+    let path = member.config.resolve_export(parsed.subpath.as_deref(), &self.conditions())?;
+    Some(path.to_string_lossy().into_owned())
+  }
 
-    let info = get_deno_info(specifier)?;
-    let redirected = follow_redirects(specifier, &info.redirects)?;
-    let local_path = info.modules.iter().find_map(|m| match m {
-      ModuleInfo::Esm { specifier: s, local, .. } if s == &redirected => Some(local.clone()),
-      _ => None,
-    });
+  /// Marks `specifier` as external, rewritten according to `options.external_specifier`.
+  async fn resolve_external(
+    &self,
+    specifier: &str,
+  ) -> Result<HookResolveIdOutput, DenoLoaderError> {
+    let id = match self.options.external_specifier {
+      ExternalSpecifier::Original => specifier.to_string(),
+      ExternalSpecifier::Resolved if specifier.starts_with("node:") => specifier.to_string(),
+      ExternalSpecifier::Cdn => self.cdn_url(specifier).await?,
+      ExternalSpecifier::Resolved => {
+        let cached = self.get_cached_info(specifier).await?;
+        match &cached.module {
+          // Pin the exact version deno picked and keep the subpath of the import.
+          Some(ModuleInfo::Npm { npm_package, .. }) => {
+            match NpmSpecifier::parse_package_ref(npm_package) {
+              Some(package) => {
+                let name = &package.name;
+                let version = package.exact_version().unwrap_or_default();
+                match NpmSpecifier::parse(specifier).and_then(|npm| npm.subpath) {
+                  Some(subpath) => format!("npm:{name}@{version}/{subpath}"),
+                  None => format!("npm:{name}@{version}"),
+                }
+              }
+              None => cached.redirected,
+            }
+          }
+          _ => cached.redirected,
+        }
+      }
+    };
+    Ok(HookResolveIdOutput { id, external: Some(true), ..Default::default() })
+  }
 
-    let result = DenoResolveResult { info, local_path, redirected };
-    self.resolve_cache.lock().unwrap().insert(specifier.to_string(), result.clone());
-    Ok(result)
+  /// Rewrites a `jsr:` or `npm:` specifier to the configured CDN, pinned to the version deno.lock
+  /// or the module graph selected. Other specifiers are returned unchanged.
+  async fn cdn_url(&self, specifier: &str) -> Result<String, DenoLoaderError> {
+    let pinned = |package_req: String| {
+      self.lockfile.as_ref()?.pinned_version(&package_req).map(ToString::to_string)
+    };
+    if let Some(jsr) = JsrSpecifier::parse(specifier) {
+      let name = jsr.package_name();
+      let version = match pinned(jsr.package_req()) {
+        Some(version) => Some(version),
+        // Module urls look like `https://jsr.io/@std/path/1.0.8/mod.ts`.
+        None => {
+          let cached = self.get_cached_info(specifier).await?;
+          cached
+            .redirected
+            .split_once(&format!("{name}/"))
+            .and_then(|(_, rest)| rest.split('/').next())
+            .map(ToString::to_string)
+        }
+      };
+      let version = version.or(jsr.version_req).unwrap_or_else(|| "latest".to_string());
+      return Ok(render_cdn_template(
+        self.options.jsr_cdn_template(),
+        &name,
+        &version,
+        jsr.subpath.as_deref(),
+      ));
+    }
+    if let Some(npm) = NpmSpecifier::parse(specifier) {
+      let package_req = match &npm.version_req {
+        Some(version_req) => format!("npm:{}@{version_req}", npm.name),
+        None => format!("npm:{}", npm.name),
+      };
+      let version = match pinned(package_req) {
+        Some(version) => Some(version),
+        None => match self.get_cached_info(specifier).await?.module {
+          Some(ModuleInfo::Npm { npm_package, .. }) => {
+            NpmSpecifier::parse_package_ref(&npm_package)
+              .and_then(|package| package.exact_version().map(ToString::to_string))
+          }
+          _ => None,
+        },
+      };
+      let version = version.or(npm.version_req).unwrap_or_else(|| "latest".to_string());
+      return Ok(render_cdn_template(
+        self.options.npm_cdn_template(),
+        &npm.name,
+        &version,
+        npm.subpath.as_deref(),
+      ));
+    }
+    Ok(specifier.to_string())
+  }
+
+  /// Looks up how deno resolved `specifier` when it was imported from the remote module `importer`.
+  async fn resolve_from_graph(&self, specifier: &str, importer: Option<&str>) -> Option<String> {
+    let importer = importer
+      .filter(|importer| importer.starts_with("http:") || importer.starts_with("https:"))?;
+    let cached = self.get_graph_info(importer).await.ok()?;
+    let Some(ModuleInfo::Esm { dependencies, .. }) = &cached.module else { return None };
+    dependencies.iter().find(|dep| dep.specifier == specifier)?.code.as_ref()?.specifier.clone()
+  }
+
+  fn register_npm_packages(&self, info: &DenoInfoJsonV1) {
+    for (id, package) in &info.npm_packages {
+      self.npm_packages.entry(id.clone()).or_insert_with(|| package.clone());
+    }
+  }
+
+  /// Resolves a file of an exact package version (`version_req` holds deno's resolved version)
+  /// from deno's global npm cache.
+  fn resolve_npm_global(&self, package: &NpmSpecifier, subpath: Option<&str>) -> Option<String> {
+    let cache = self.npm_cache.as_ref()?;
+    let version = package.exact_version()?;
+    let dir = cache.package_dir(&package.name, version);
+    let path = PackageJson::load(&dir)?.resolve(&dir, subpath, &self.conditions())?;
+    Some(path.to_string_lossy().into_owned())
+  }
+
+  /// Resolves a file of the package `package_id` from the `node_modules/.deno` layout deno
+  /// materializes in `auto` mode next to deno.json. Imports made from inside the package then
+  /// resolve through the symlinks deno creates next to it, like in any node_modules folder.
+  fn resolve_npm_local(
+    &self,
+    package_id: &str,
+    package: &NpmSpecifier,
+    subpath: Option<&str>,
+  ) -> Option<String> {
+    let root = match &self.config {
+      Some(config) => config.dir().to_path_buf(),
+      None => self.options.working_dir(),
+    };
+    let dir = root
+      .join("node_modules")
+      .join(".deno")
+      .join(package_id.replace('/', "+"))
+      .join("node_modules")
+      .join(&package.name);
+    let path = PackageJson::load(&dir)?.resolve(&dir, subpath, &self.conditions())?;
+    Some(path.to_string_lossy().into_owned())
+  }
+
+  /// Resolves a bare import made from a file inside a package of deno's global npm cache, using the
+  /// dependency versions deno picked for that package.
+  fn resolve_npm_dependency(&self, specifier: &str, importer: &str) -> Option<String> {
+    // Relative, absolute, `#imports` and `scheme:` specifiers don't name a package.
+    if specifier.starts_with(['.', '/', '#'])
+      || Path::new(specifier).is_absolute()
+      || specifier.contains(':')
+    {
+      return None;
+    }
+    let cache = self.npm_cache.as_ref()?;
+    let (name, version, dir) = cache.package_of(Path::new(importer))?;
+    let dependency = NpmSpecifier::parse_package_ref(specifier)?;
+    let dependency_version = if dependency.name == name {
+      Some(version)
+    } else {
+      self
+        .npm_packages
+        .iter()
+        .find(|package| package.name == name && package.version == version)
+        .and_then(|package| {
+          package.dependencies.iter().find_map(|id| {
+            let resolved = NpmSpecifier::parse_package_ref(id)?;
+            (resolved.name == dependency.name).then_some(resolved.version_req)?
+          })
+        })
+    };
+    // Packages resolved without deno info (e.g. from the disk cache) fall back to their own
+    // package.json ranges.
+    let dependency_version = dependency_version.or_else(|| {
+      let package_json = PackageJson::load(&dir)?;
+      cache.best_cached_version(&dependency.name, package_json.dependency_req(&dependency.name))
+    })?;
+
+    let subpath = dependency.subpath.clone();
+    let package =
+      NpmSpecifier { version_req: Some(dependency_version), subpath: None, ..dependency };
+    self.resolve_npm_global(&package, subpath.as_deref())
+  }
+
+  async fn disk_cache(&self) -> Option<&DiskCache> {
+    self
+      .disk_cache
+      .get_or_init(|| async {
+        let root = self.options.cache_dir.as_ref()?;
+        let deno_version = self.provider.version().await.ok()?;
+        // Resolved independently of `read_deno_dir`, cached `local_path`s point into it either way.
+        let deno_dir = DenoDir::from_options(&self.options)
+          .map(|deno_dir| deno_dir.root.to_string_lossy().into_owned())
+          .unwrap_or_default();
+        let mut env = self
+          .options
+          .env
+          .iter()
+          .map(|(name, value)| format!("{name}={value}"))
+          .collect::<Vec<_>>();
+        env.sort();
+        let config = self.options.config.as_ref().map(|path| path.to_string_lossy().into_owned());
+        let member_import_maps = self
+          .workspace
+          .iter()
+          .map(|member| {
+            format!("{}\n{}", member.config.import_map_url, member.config.import_map_string)
+          })
+          .collect::<Vec<_>>();
+        let lockfile = self.lockfile_path.as_ref().and_then(|path| std::fs::read(path).ok());
+        DiskCache::open(
+          root,
+          &[
+            deno_version.as_bytes(),
+            deno_dir.as_bytes(),
+            env.join("\n").as_bytes(),
+            self.options.extra_args.join("\n").as_bytes(),
+            config.unwrap_or_default().as_bytes(),
+            self.import_map_string.as_bytes(),
+            member_import_maps.join("\n").as_bytes(),
+            lockfile.as_deref().unwrap_or_default(),
+          ],
+        )
+        .ok()
+      })
+      .await
+      .as_ref()
+  }
+
+  /// Caches every module and redirect source of `info`, so later lookups of any module in the graph
+  /// don't need another `deno info` call.
+  async fn seed_from_graph(&self, info: &DenoInfoJsonV1) {
+    let disk_cache = self.disk_cache().await;
+    let specifiers = info
+      .modules
+      .iter()
+      .map(ModuleInfo::specifier)
+      .chain(info.redirects.keys().map(String::as_str));
+
+    for specifier in specifiers {
+      let mut cell = self.resolve_cache.entry(specifier.to_string()).or_default().clone();
+      if cell.get().is_some_and(|cached| !cached.has_dependencies) {
+        // Keep the dependencies for imports made from modules resolved without deno.
+        cell = self.graph_cache.entry(specifier.to_string()).or_default().clone();
+      }
+      if cell.initialized() {
+        continue;
+      }
+      // Redirect cycles are reported when the specifier is actually requested. Cells that are being
+      // initialized by another lookup reject the value, which is fine as both come from deno info.
+      if let Ok(result) = DenoResolveResult::from_graph(specifier, info) {
+        if let Some(cache) = disk_cache.filter(|_| !result.is_error()) {
+          let _ = cache.set(specifier, &result);
+        }
+        let _ = cell.set(result);
+      }
+    }
   }
 }
 
@@ -125,46 +571,138 @@ impl Plugin for DenoLoaderPlugin {
     Cow::Borrowed("builtin:deno-loader")
   }
 
+  fn build_start(
+    &self,
+    ctx: &PluginContext,
+    args: &HookBuildStartArgs<'_>,
+  ) -> impl std::future::Future<Output = HookNoopReturn> + Send {
+    async {
+      if self.import_map.get().is_none() {
+        // No config or `cwd` option told where relative import map entries point to.
+        let url = directory_url(ctx.cwd());
+        let import_map = match parse_import_map(url, &self.import_map_string) {
+          Ok((import_map, warnings)) => {
+            self.config_warnings.lock().unwrap().extend(warnings);
+            Some(import_map)
+          }
+          Err(err) => {
+            self.config_error.lock().unwrap().get_or_insert(Arc::new(err));
+            None
+          }
+        };
+        let _ = self.import_map.set(import_map);
+      }
+      for message in std::mem::take(&mut *self.config_warnings.lock().unwrap()) {
+        ctx.warn(LogWithoutPlugin { message, ..Default::default() });
+      }
+      // Kept, so rebuilds of a reused plugin keep failing instead of ignoring the config.
+      if let Some(err) = self.config_error.lock().unwrap().clone() {
+        return Err(err.into());
+      }
+      if self.options.cwd.is_none()
+        && self.options.config.is_none()
+        && self.options.import_map_string.is_none()
+      {
+        // Discovery ran from the process directory, point out a different config next to
+        // rolldown's `cwd` instead of silently ignoring it.
+        let discovered = self.config.as_ref().map(|config| config.path.as_path());
+        if let Some(path) =
+          DenoConfig::find(ctx.cwd()).filter(|path| Some(path.as_path()) != discovered)
+        {
+          let message = format!(
+            "{} is ignored, deno.json is discovered from the process directory unless `cwd` is set",
+            path.display()
+          );
+          ctx.warn(LogWithoutPlugin { message, ..Default::default() });
+        }
+      }
+      if self.options.conditions.is_none() {
+        *self.conditions.lock().unwrap() = match &args.options.resolve.condition_names {
+          Some(condition_names) => condition_names.clone(),
+          None => platform_conditions(args.options.platform),
+        };
+      }
+      if !self.options.prefetch_graph {
+        return Ok(());
+      }
+
+      let specifiers = args
+        .options
+        .input
+        .iter()
+        .filter_map(|input| {
+          if url::Url::parse(&input.import).is_ok() {
+            Some(input.import.clone())
+          } else {
+            url::Url::from_file_path(args.options.cwd.join(&input.import))
+              .ok()
+              .map(|url| url.to_string())
+          }
+        })
+        .collect::<Vec<_>>();
+
+      // Entries already in the disk cache were prefetched by a previous build.
+      let disk_cache = self.disk_cache().await;
+      let specifiers = specifiers
+        .iter()
+        .filter(|specifier| disk_cache.is_none_or(|cache| cache.get(specifier).is_none()));
+
+      let infos = join_all(specifiers.map(|specifier| self.provider.info(specifier))).await;
+      // Failures are not fatal here, the same lookup is retried lazily from `resolve_id` where the
+      // error can be reported together with the importing module.
+      for info in infos.into_iter().flatten() {
+        self.register_npm_packages(&info);
+        self.seed_from_graph(&info).await;
+      }
+
+      Ok(())
+    }
+  }
+
   fn resolve_id(
     &self,
     ctx: &PluginContext,
     args: &HookResolveIdArgs<'_>,
   ) -> impl std::future::Future<Output = HookResolveIdReturn> {
     async {
-      let id = if args.specifier.starts_with('.') {
-        args
-          .importer
-          .and_then(|importer| url::Url::parse(importer).ok())
-          .and_then(|base_url| base_url.join(&args.specifier).ok())
-          .map(|joined_url| {
-            if joined_url.scheme() == "file" {
-              joined_url.path().to_string()
-            } else {
-              joined_url.to_string()
-            }
-          })
-          .unwrap_or_else(|| args.specifier.to_string())
-      } else {
-        args.specifier.to_string()
-      };
+      if let Some(importer) = args.importer {
+        if let Some(id) = self.resolve_npm_dependency(args.specifier, importer) {
+          return Ok(Some(HookResolveIdOutput { id, ..Default::default() }));
+        }
+      }
 
-      let base_url = ctx
-        .cwd()
-        .to_str()
-        .and_then(|s| url::Url::from_file_path(s).ok())
-        .unwrap_or_else(|| url::Url::parse("file:///").unwrap());
+      // Imports of remote modules were already resolved by deno, including import maps of jsr
+      // packages and redirects, so prefer its answer over re-resolving the specifier here.
+      let (maybe_resolved, import_mapped) =
+        match self.resolve_from_graph(args.specifier, args.importer).await {
+          Some(resolved) => (resolved, false),
+          None => match self.map_import(args.specifier, args.importer, ctx.cwd()) {
+            Some(url) => (url.to_string(), true),
+            None => (args.specifier.to_string(), false),
+          },
+        };
 
-      let import_map =
-        parse_from_json(base_url.clone(), &self.import_map_string).unwrap().import_map;
+      if let Some(id) = self.resolve_workspace_member(&maybe_resolved) {
+        return Ok(Some(HookResolveIdOutput { id, ..Default::default() }));
+      }
 
-      let maybe_resolved = import_map
-        .resolve(&id, &base_url)
-        .ok()
-        .map(|url| url.to_string())
-        .unwrap_or_else(|| id.to_string());
+      let is_remote = ["jsr:", "npm:", "node:", "http:", "https:"]
+        .iter()
+        .any(|scheme| maybe_resolved.starts_with(scheme));
+      if is_remote && self.external.matches(&maybe_resolved) {
+        return Ok(Some(
+          self
+            .resolve_external(&maybe_resolved)
+            .await
+            .map_err(|err| err.in_resolve(args.specifier, args.importer))?,
+        ));
+      }
 
       if maybe_resolved.starts_with("jsr:") {
-        let cached: DenoResolveResult = self.get_cached_info(&maybe_resolved).expect("info failed");
+        let cached = self
+          .get_cached_info(&maybe_resolved)
+          .await
+          .map_err(|err| err.in_resolve(args.specifier, args.importer))?;
 
         return Ok(Some(HookResolveIdOutput {
           id: cached.redirected,
@@ -172,16 +710,33 @@ impl Plugin for DenoLoaderPlugin {
           ..Default::default()
         }));
       } else if maybe_resolved.starts_with("npm:") {
-        let cached: DenoResolveResult = self.get_cached_info(&maybe_resolved).expect("info failed");
+        let cached = self
+          .get_cached_info(&maybe_resolved)
+          .await
+          .map_err(|err| err.in_resolve(args.specifier, args.importer))?;
 
-        if let Some(ModuleInfo::Npm { npm_package, .. }) = cached.info.modules.into_iter().find(
-          |m| matches!(m, ModuleInfo::Npm { specifier, .. } if specifier == &cached.redirected),
-        ) {
-          let package_name = npm_package.split('@').next().unwrap_or(&npm_package).to_string();
+        if let Some(ModuleInfo::Npm { npm_package, .. }) = &cached.module {
+          // deno reports the exact package, the subpath only survives in the specifier itself.
+          let Some(package) = NpmSpecifier::parse_package_ref(npm_package) else {
+            return Ok(None);
+          };
+          let subpath = NpmSpecifier::parse(&maybe_resolved).and_then(|npm| npm.subpath);
+          let resolved = match self.node_modules_dir {
+            NodeModulesDirMode::None => self.resolve_npm_global(&package, subpath.as_deref()),
+            NodeModulesDirMode::Auto => {
+              self.resolve_npm_local(npm_package, &package, subpath.as_deref())
+            }
+            // node_modules is managed by another package manager, resolve it like node does.
+            NodeModulesDirMode::Manual => None,
+          };
+          if let Some(id) = resolved {
+            return Ok(Some(HookResolveIdOutput { id, ..Default::default() }));
+          }
+          let bare_specifier = NpmSpecifier { subpath, ..package }.bare_specifier();
           return Ok(
             ctx
               .resolve(
-                &package_name,
+                &bare_specifier,
                 args.importer,
                 Some(PluginContextResolveOptions {
                   import_kind: args.kind,
@@ -201,6 +756,31 @@ impl Plugin for DenoLoaderPlugin {
           external: Some(false),
           ..Default::default()
         }));
+      } else if import_mapped && maybe_resolved.starts_with("file:") {
+        // Import map targets like `"@/": "./src/"`, everything else local is left to rolldown as
+        // written. `to_file_path` also percent-decodes the url.
+        let Some(path) =
+          url::Url::parse(&maybe_resolved).ok().and_then(|url| url.to_file_path().ok())
+        else {
+          return Ok(None);
+        };
+        // Let rolldown finish the resolution, so extension and directory index lookup still apply.
+        return Ok(
+          ctx
+            .resolve(
+              &path.to_string_lossy(),
+              args.importer,
+              Some(PluginContextResolveOptions {
+                import_kind: args.kind,
+                skip_self: true,
+                custom: Arc::clone(&args.custom),
+              }),
+            )
+            .await?
+            .map(|resolved_id| {
+              Some(HookResolveIdOutput { id: resolved_id.id.to_string(), ..Default::default() })
+            })?,
+        );
       }
 
       Ok(None)
@@ -217,17 +797,31 @@ impl Plugin for DenoLoaderPlugin {
         || args.id.starts_with("http:")
         || args.id.starts_with("https:")
       {
-        let cached = self.get_cached_info(args.id).expect("info failed");
-        let local_path = cached.local_path.expect("local path not found");
+        let cached = self.get_cached_info(args.id).await.map_err(|err| err.in_load(args.id))?;
+        // Sources are returned as strings, which would corrupt the binary.
+        if cached.media_type == Some(DenoMediaType::Wasm) {
+          return Err(
+            DenoLoaderError::WasmNotSupported { specifier: cached.redirected }
+              .in_load(args.id)
+              .into(),
+          );
+        }
+        let local_path = cached.local_path.ok_or_else(|| {
+          DenoLoaderError::LocalPathNotFound { specifier: cached.redirected.clone() }
+            .in_load(args.id)
+        })?;
+        let source =
+          OsFileSystem::read(&OsFileSystem, Path::new(&local_path)).map_err(|source| {
+            DenoLoaderError::ReadCacheFile { path: local_path.clone(), source }.in_load(args.id)
+          })?;
         // Return the specifier as the id to tell rolldown that this data url is handled by the plugin. Don't fallback to
         // the default resolve behavior and mark it as external.
         Ok(Some(HookLoadOutput {
-          code: String::from_utf8_lossy(
-            &OsFileSystem::read(&OsFileSystem, Path::new(&local_path))
-              .expect("cant read local path"),
-          )
-          .into_owned(),
-          module_type: Some(ModuleType::Tsx),
+          code: String::from_utf8_lossy(strip_cache_metadata(&source)).into_owned(),
+          // Without a known media type keep treating the source as tsx, the most permissive syntax.
+          module_type: Some(
+            cached.media_type.as_ref().map_or(ModuleType::Tsx, DenoMediaType::module_type),
+          ),
           ..Default::default()
         }))
       } else {
@@ -236,3 +830,346 @@ impl Plugin for DenoLoaderPlugin {
     }
   }
 }
+
+fn directory_url(dir: &Path) -> url::Url {
+  let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+  url::Url::from_directory_path(dir).unwrap_or_else(|()| url::Url::parse("file:///").unwrap())
+}
+
+/// Url of a module id as the import map sees it: remote modules keep their url, local modules are
+/// file paths.
+fn importer_url(importer: &str) -> Option<url::Url> {
+  if Path::new(importer).is_absolute() {
+    url::Url::from_file_path(importer).ok()
+  } else {
+    url::Url::parse(importer).ok()
+  }
+}
+
+/// Fills the `{name}`, `{version}` and `{subpath}` placeholders of a CDN url template.
+fn render_cdn_template(template: &str, name: &str, version: &str, subpath: Option<&str>) -> String {
+  template
+    .replace("{name}", name)
+    .replace("{version}", version)
+    .replace("{subpath}", &subpath.map(|subpath| format!("/{subpath}")).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::provider::StaticDenoInfoProvider;
+  use crate::test_util::TempDir;
+  use serde_json::json;
+
+  /// Options that keep a plugin away from deno and any cache on disk.
+  fn static_options() -> DenoLoaderOptions {
+    DenoLoaderOptions {
+      import_map_string: Some("{}".to_string()),
+      prefetch_graph: false,
+      read_deno_dir: false,
+      native_jsr_resolution: false,
+      ..Default::default()
+    }
+  }
+
+  /// A plugin that serves every lookup from `info`.
+  fn static_plugin(info: serde_json::Value) -> DenoLoaderPlugin {
+    static_plugin_with(static_options(), info)
+  }
+
+  fn static_plugin_with(options: DenoLoaderOptions, info: serde_json::Value) -> DenoLoaderPlugin {
+    let info: DenoInfoJsonV1 = serde_json::from_value(info).unwrap();
+    DenoLoaderPlugin::with_options(options).with_provider(StaticDenoInfoProvider::new(info))
+  }
+
+  #[tokio::test]
+  async fn static_provider_follows_redirects() {
+    let plugin = static_plugin(json!({
+      "version": 1,
+      "roots": ["https://example.com/latest/mod.ts"],
+      "redirects": {
+        "https://example.com/latest/mod.ts": "https://example.com/v2/mod.ts",
+        "https://example.com/v2/mod.ts": "https://example.com/v2.1/mod.ts"
+      },
+      "modules": [{
+        "kind": "esm",
+        "specifier": "https://example.com/v2.1/mod.ts",
+        "local": "/deno/remote/https/example.com/abc",
+        "mediaType": "TypeScript"
+      }]
+    }));
+
+    let result = plugin.get_cached_info("https://example.com/latest/mod.ts").await.unwrap();
+    assert_eq!(result.redirected, "https://example.com/v2.1/mod.ts");
+    assert_eq!(result.local_path.as_deref(), Some("/deno/remote/https/example.com/abc"));
+    assert_eq!(result.media_type, Some(DenoMediaType::TypeScript));
+    assert!(result.has_dependencies);
+  }
+
+  #[tokio::test]
+  async fn static_provider_reports_circular_redirects() {
+    let plugin = static_plugin(json!({
+      "redirects": {
+        "https://example.com/a.ts": "https://example.com/b.ts",
+        "https://example.com/b.ts": "https://example.com/a.ts"
+      }
+    }));
+
+    let err = plugin.get_cached_info("https://example.com/a.ts").await.unwrap_err();
+    assert!(
+      matches!(
+        &err,
+        DenoLoaderError::CircularRedirect { specifier } if specifier == "https://example.com/a.ts"
+      ),
+      "{err}"
+    );
+  }
+
+  #[test]
+  fn import_maps_outside_the_entry_directory() {
+    let tmp = TempDir::new();
+    let config = tmp.write(
+      "config/deno.json",
+      r#"{
+        "imports": { "@/": "./src/", "lodash": "npm:lodash@4" },
+        "scopes": {
+          "./vendor/": { "lodash": "npm:lodash@3" },
+          "../app/": { "lodash": "npm:lodash@2" }
+        }
+      }"#,
+    );
+    let app = tmp.path().join("app");
+    let plugin = DenoLoaderPlugin::with_options(DenoLoaderOptions {
+      config: Some(config),
+      prefetch_graph: false,
+      read_deno_dir: false,
+      native_jsr_resolution: false,
+      ..Default::default()
+    });
+    let map = |specifier: &str, importer: Option<&Path>| {
+      let importer = importer.map(|importer| importer.to_string_lossy().into_owned());
+      plugin.map_import(specifier, importer.as_deref(), &app).map(|url| url.to_string())
+    };
+    let file_url = |path: PathBuf| url::Url::from_file_path(path).unwrap().to_string();
+
+    // Targets are relative to the config, entries are imported from rolldown's cwd.
+    assert_eq!(map("@/util.ts", None), Some(file_url(tmp.path().join("config/src/util.ts"))));
+    assert_eq!(map("lodash", None).as_deref(), Some("npm:lodash@2"));
+    let config_module = tmp.path().join("config/main.ts");
+    assert_eq!(map("lodash", Some(&config_module)).as_deref(), Some("npm:lodash@4"));
+    // Scopes match the importer, relative to the config as well.
+    let vendored = tmp.path().join("config/vendor/mod.ts");
+    assert_eq!(map("lodash", Some(&vendored)).as_deref(), Some("npm:lodash@3"));
+  }
+
+  #[test]
+  fn only_matched_import_map_entries_are_mapped() {
+    let tmp = TempDir::new();
+    let plugin = DenoLoaderPlugin::with_options(DenoLoaderOptions {
+      import_map_string: Some(
+        json!({
+          "imports": {
+            "@/": "./src/",
+            "./legacy.ts": "./modern.ts",
+            "https://example.com/": "./vendor/"
+          }
+        })
+        .to_string(),
+      ),
+      cwd: Some(tmp.path().to_path_buf()),
+      prefetch_graph: false,
+      read_deno_dir: false,
+      native_jsr_resolution: false,
+      ..Default::default()
+    });
+    let importer = tmp.path().join("main.ts").to_string_lossy().into_owned();
+    let map = |specifier: &str| {
+      plugin.map_import(specifier, Some(&importer), tmp.path()).map(|url| url.to_string())
+    };
+    let file_url =
+      |path: &str| url::Url::from_file_path(tmp.path().join(path)).unwrap().to_string();
+
+    assert_eq!(map("@/util.ts"), Some(file_url("src/util.ts")));
+    assert_eq!(map("./legacy.ts"), Some(file_url("modern.ts")));
+    assert_eq!(map("https://example.com/mod.ts"), Some(file_url("vendor/mod.ts")));
+    // Left to rolldown and the jsr, npm and http handling as written.
+    assert_eq!(map("./util.ts"), None);
+    assert_eq!(map("../util.ts"), None);
+    assert_eq!(map("react"), None);
+    assert_eq!(map("jsr:@std/path@1"), None);
+    assert_eq!(map("https://example.org/mod.ts"), None);
+  }
+
+  #[test]
+  fn workspace_members() {
+    let tmp = TempDir::new();
+    tmp.write(
+      "deno.json",
+      json!({
+        "workspace": ["./packages/a", "./packages/b"],
+        "imports": { "shared": "./shared/mod.ts", "lodash": "npm:lodash@3" }
+      })
+      .to_string(),
+    );
+    tmp.write(
+      "packages/a/deno.json",
+      r#"{ "name": "@acme/a", "exports": "./mod.ts", "imports": { "lodash": "npm:lodash@4" } }"#,
+    );
+    tmp.write(
+      "packages/b/deno.json",
+      r#"{ "name": "@acme/b", "exports": { ".": "./mod.ts", "./util": "./util.ts" } }"#,
+    );
+    let a_mod = tmp.write("packages/a/mod.ts", "");
+    let b_mod = tmp.write("packages/b/mod.ts", "");
+    let b_util = tmp.write("packages/b/util.ts", "");
+    // Discovered from a member, like running the build inside packages/a.
+    let plugin = DenoLoaderPlugin::with_options(DenoLoaderOptions {
+      cwd: Some(tmp.path().join("packages/a")),
+      prefetch_graph: false,
+      read_deno_dir: false,
+      native_jsr_resolution: false,
+      ..Default::default()
+    });
+    let path = |path: PathBuf| Some(path.to_string_lossy().into_owned());
+
+    assert_eq!(plugin.resolve_workspace_member("@acme/a"), path(a_mod.clone()));
+    assert_eq!(plugin.resolve_workspace_member("@acme/b"), path(b_mod.clone()));
+    assert_eq!(plugin.resolve_workspace_member("jsr:@acme/b@1/util"), path(b_util));
+    assert_eq!(plugin.resolve_workspace_member("@acme/c"), None);
+
+    let map = |specifier: &str, importer: &Path| {
+      let importer = importer.to_string_lossy();
+      plugin.map_import(specifier, Some(&importer), tmp.path()).map(|url| url.to_string())
+    };
+    let shared = url::Url::from_file_path(tmp.path().join("shared/mod.ts")).unwrap().to_string();
+    // Members use their own import map first and fall back to the root's.
+    assert_eq!(map("lodash", &a_mod).as_deref(), Some("npm:lodash@4"));
+    assert_eq!(map("lodash", &b_mod).as_deref(), Some("npm:lodash@3"));
+    assert_eq!(map("shared", &a_mod), Some(shared));
+  }
+
+  fn external_graph() -> serde_json::Value {
+    json!({
+      "roots": ["npm:preact@10/hooks", "npm:react-dom@18", "jsr:@std/path@1"],
+      "redirects": {
+        "npm:preact@10/hooks": "npm:/preact@10.24.3/hooks",
+        "npm:react-dom@18": "npm:/react-dom@18.3.1",
+        "npm:react-dom@18/client": "npm:/react-dom@18.3.1/client",
+        "jsr:@std/path@1": "https://jsr.io/@std/path/1.0.8/mod.ts"
+      },
+      "modules": [
+        { "kind": "npm", "specifier": "npm:/preact@10.24.3/hooks", "npmPackage": "preact@10.24.3" },
+        {
+          "kind": "npm",
+          "specifier": "npm:/react-dom@18.3.1",
+          "npmPackage": "react-dom@18.3.1_react@18.3.1"
+        },
+        {
+          "kind": "npm",
+          "specifier": "npm:/react-dom@18.3.1/client",
+          "npmPackage": "react-dom@18.3.1_react@18.3.1"
+        },
+        {
+          "kind": "esm",
+          "specifier": "https://jsr.io/@std/path/1.0.8/mod.ts",
+          "local": "/deno/remote/https/jsr.io/abc",
+          "mediaType": "TypeScript"
+        }
+      ]
+    })
+  }
+
+  #[tokio::test]
+  async fn external_original_specifiers() {
+    let plugin = static_plugin(external_graph());
+    for specifier in ["npm:preact@10/hooks", "jsr:@std/path@1", "node:fs"] {
+      let output = plugin.resolve_external(specifier).await.unwrap();
+      assert_eq!(output.id, specifier);
+      assert_eq!(output.external, Some(true));
+    }
+  }
+
+  #[tokio::test]
+  async fn external_resolved_specifiers() {
+    let options =
+      DenoLoaderOptions { external_specifier: ExternalSpecifier::Resolved, ..static_options() };
+    let plugin = static_plugin_with(options, external_graph());
+    let expected = [
+      ("npm:preact@10/hooks", "npm:preact@10.24.3/hooks"),
+      // The peer dependency suffix of deno's package id is dropped.
+      ("npm:react-dom@18", "npm:react-dom@18.3.1"),
+      ("jsr:@std/path@1", "https://jsr.io/@std/path/1.0.8/mod.ts"),
+      ("node:fs", "node:fs"),
+    ];
+    for (specifier, id) in expected {
+      let output = plugin.resolve_external(specifier).await.unwrap();
+      assert_eq!(output.id, id);
+      assert_eq!(output.external, Some(true));
+    }
+  }
+
+  #[test]
+  fn cdn_templates() {
+    let template = "https://esm.sh/{name}@{version}{subpath}";
+    assert_eq!(
+      render_cdn_template(template, "preact", "10.24.3", None),
+      "https://esm.sh/preact@10.24.3"
+    );
+    assert_eq!(
+      render_cdn_template(template, "@types/node", "22.10.2", Some("fs/promises")),
+      "https://esm.sh/@types/node@22.10.2/fs/promises"
+    );
+    assert_eq!(
+      render_cdn_template(
+        "https://cdn.example/{name}/{version}?path={subpath}",
+        "a",
+        "1",
+        Some("b")
+      ),
+      "https://cdn.example/a/1?path=/b"
+    );
+  }
+
+  #[tokio::test]
+  async fn cdn_urls() {
+    let tmp = TempDir::new();
+    tmp.write(
+      "deno.lock",
+      json!({
+        "version": "4",
+        "specifiers": {
+          "npm:preact@10": "10.19.0",
+          "npm:@emotion/react@11": "11.11.4_react@18.3.1",
+          "jsr:@std/path@1": "1.0.6"
+        }
+      })
+      .to_string(),
+    );
+    let options = DenoLoaderOptions {
+      cwd: Some(tmp.path().to_path_buf()),
+      external_specifier: ExternalSpecifier::Cdn,
+      jsr_cdn_template: Some("https://cdn.example/jsr/{name}@{version}{subpath}".to_string()),
+      ..static_options()
+    };
+    let locked = static_plugin_with(options, external_graph());
+    let unlocked = static_plugin(external_graph());
+
+    let expected = [
+      // deno.lock wins over the module graph.
+      (&locked, "npm:preact@10/hooks", "https://esm.sh/preact@10.19.0/hooks"),
+      (&unlocked, "npm:preact@10/hooks", "https://esm.sh/preact@10.24.3/hooks"),
+      (&locked, "jsr:@std/path@1", "https://cdn.example/jsr/@std/path@1.0.6"),
+      (&unlocked, "jsr:@std/path@1", "https://esm.sh/jsr/@std/path@1.0.8"),
+      (&locked, "npm:@emotion/react@11", "https://esm.sh/@emotion/react@11.11.4"),
+      (&unlocked, "npm:react-dom@18/client", "https://esm.sh/react-dom@18.3.1/client"),
+      // Neither pins a version, the requested range or `latest` is kept.
+      (&unlocked, "npm:lodash@^4", "https://esm.sh/lodash@^4"),
+      (&unlocked, "npm:@types/node/fs", "https://esm.sh/@types/node@latest/fs"),
+      (&unlocked, "jsr:@std/fs", "https://esm.sh/jsr/@std/fs@latest"),
+      (&unlocked, "https://example.com/mod.ts", "https://example.com/mod.ts"),
+    ];
+    for (plugin, specifier, url) in expected {
+      assert_eq!(plugin.cdn_url(specifier).await.unwrap(), url, "{specifier}");
+    }
+  }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rolldown_utils::xxhash::xxhash_base64_url;
+
+use crate::deno_info::DenoResolveResult;
+
+/// Resolution results persisted across builds.
+///
+/// Entries live in a directory named after a hash of everything that changes what deno resolves,
+/// e.g. the deno version, `DENO_DIR`, the flags, the import maps and the lockfile, so changing any
+/// of them starts from an empty cache instead of serving stale entries.
+#[derive(Debug)]
+pub struct DiskCache {
+  dir: PathBuf,
+}
+
+impl DiskCache {
+  /// Opens the cache for the inputs in `key_parts`.
+  pub fn open(root: &Path, key_parts: &[&[u8]]) -> std::io::Result<Self> {
+    let mut key = Vec::new();
+    for part in key_parts {
+      key.extend_from_slice(part);
+      key.push(0);
+    }
+    let dir = root.join(xxhash_base64_url(&key));
+    std::fs::create_dir_all(&dir)?;
+    Ok(Self { dir })
+  }
+
+  fn entry_path(&self, specifier: &str) -> PathBuf {
+    self.dir.join(format!("{}.json", xxhash_base64_url(specifier.as_bytes())))
+  }
+
+  /// Unreadable or corrupt entries are treated as misses, as are entries whose module was removed
+  /// from deno's cache since, e.g. by `deno clean`.
+  pub fn get(&self, specifier: &str) -> Option<DenoResolveResult> {
+    let bytes = std::fs::read(self.entry_path(specifier)).ok()?;
+    let result: DenoResolveResult = serde_json::from_slice(&bytes).ok()?;
+    if result.local_path.as_ref().is_some_and(|path| !Path::new(path).is_file()) {
+      return None;
+    }
+    Some(result)
+  }
+
+  pub fn set(&self, specifier: &str, result: &DenoResolveResult) -> std::io::Result<()> {
+    let path = self.entry_path(specifier);
+    // Write to a temporary file first so concurrent builds never observe a partial entry.
+    // The counter keeps concurrent writes of the same process apart.
+    static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+    let tmp = path.with_extension(format!(
+      "{}.{}.tmp",
+      std::process::id(),
+      NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, serde_json::to_vec(result)?)?;
+    std::fs::rename(tmp, path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::deno_info::{DenoMediaType, ModuleInfo};
+  use crate::test_util::TempDir;
+
+  fn result(local_path: &Path) -> DenoResolveResult {
+    let local = local_path.to_string_lossy().into_owned();
+    DenoResolveResult {
+      redirected: "https://example.com/mod.ts".to_string(),
+      local_path: Some(local.clone()),
+      media_type: Some(DenoMediaType::TypeScript),
+      module: Some(ModuleInfo::esm(
+        "https://example.com/mod.ts".to_string(),
+        local,
+        DenoMediaType::TypeScript,
+      )),
+      importer_chain: Vec::new(),
+      has_dependencies: true,
+    }
+  }
+
+  #[test]
+  fn get_returns_set_entries() {
+    let tmp = TempDir::new();
+    let module = tmp.write("deno/mod.ts", "export {};");
+    let cache = DiskCache::open(&tmp.path().join("cache"), &[b"deno 2.1.4", b"{}"]).unwrap();
+
+    assert!(cache.get("https://example.com/mod.ts").is_none());
+    cache.set("https://example.com/mod.ts", &result(&module)).unwrap();
+    let cached = cache.get("https://example.com/mod.ts").unwrap();
+    assert_eq!(cached.redirected, "https://example.com/mod.ts");
+    assert_eq!(cached.local_path.as_deref(), module.to_str());
+    assert!(cached.has_dependencies);
+    assert!(cache.get("https://example.com/other.ts").is_none());
+
+    // The same inputs reopen the same entries.
+    let reopened = DiskCache::open(&tmp.path().join("cache"), &[b"deno 2.1.4", b"{}"]).unwrap();
+    assert!(reopened.get("https://example.com/mod.ts").is_some());
+  }
+
+  #[test]
+  fn changed_inputs_start_empty() {
+    let tmp = TempDir::new();
+    let module = tmp.write("deno/mod.ts", "export {};");
+    let root = tmp.path().join("cache");
+    DiskCache::open(&root, &[b"deno 2.1.4", b"{}"])
+      .unwrap()
+      .set("https://example.com/mod.ts", &result(&module))
+      .unwrap();
+
+    let changed: [&[&[u8]]; 2] = [&[b"deno 2.1.5", b"{}"], &[b"deno 2.1.4", br#"{"imports":{}}"#]];
+    for key_parts in changed {
+      let cache = DiskCache::open(&root, key_parts).unwrap();
+      assert!(cache.get("https://example.com/mod.ts").is_none());
+    }
+    // Parts are separated, moving bytes between them is a different key.
+    let shifted = DiskCache::open(&root, &[b"deno 2.1.4{", b"}"]).unwrap();
+    assert!(shifted.get("https://example.com/mod.ts").is_none());
+  }
+
+  #[test]
+  fn deleted_modules_are_misses() {
+    let tmp = TempDir::new();
+    let module = tmp.write("deno/mod.ts", "export {};");
+    let cache = DiskCache::open(&tmp.path().join("cache"), &[b"deno 2.1.4"]).unwrap();
+    cache.set("https://example.com/mod.ts", &result(&module)).unwrap();
+
+    std::fs::remove_file(&module).unwrap();
+    assert!(cache.get("https://example.com/mod.ts").is_none());
+  }
+
+  #[test]
+  fn corrupt_entries_are_misses() {
+    let tmp = TempDir::new();
+    let cache = DiskCache::open(&tmp.path().join("cache"), &[b"deno 2.1.4"]).unwrap();
+    std::fs::write(cache.entry_path("https://example.com/mod.ts"), "{").unwrap();
+    assert!(cache.get("https://example.com/mod.ts").is_none());
+  }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::process::ExitStatus;
+
+#[derive(Debug)]
+pub enum DenoLoaderError {
+  /// The deno executable could not be spawned, usually because it is not installed or not on PATH.
+  DenoNotFound {
+    executable: String,
+    source: std::io::Error,
+  },
+  /// `deno info` ran but exited with a non-zero status.
+  DenoInfoFailed {
+    specifier: String,
+    status: ExitStatus,
+    stderr: String,
+  },
+  /// `deno info --json` produced output that doesn't match the expected schema.
+  InvalidDenoInfoJson {
+    specifier: String,
+    source: serde_json::Error,
+  },
+  /// The graph file passed to `StaticDenoInfoProvider::from_file` could not be read.
+  ReadGraphFile {
+    path: String,
+    source: std::io::Error,
+  },
+  /// The graph file passed to `StaticDenoInfoProvider::from_file` is not `deno info --json` output.
+  InvalidGraphFile {
+    path: String,
+    source: serde_json::Error,
+  },
+  CircularRedirect {
+    specifier: String,
+  },
+  LocalPathNotFound {
+    specifier: String,
+  },
+  ReadCacheFile {
+    path: String,
+    source: std::io::Error,
+  },
+  ReadConfig {
+    path: String,
+    source: std::io::Error,
+  },
+  InvalidConfig {
+    path: String,
+    source: serde_json::Error,
+  },
+  InvalidImportMap {
+    url: String,
+    message: String,
+  },
+  /// An entry of `options.external` is not a valid regular expression.
+  InvalidExternalPattern {
+    pattern: String,
+    source: regex::Error,
+  },
+  /// A remote wasm module was imported, its bytes can't be returned as source code.
+  WasmNotSupported {
+    specifier: String,
+  },
+  /// deno could not fetch or parse a module of the graph.
+  ModuleError {
+    specifier: String,
+    error: String,
+    importer_chain: Vec<String>,
+  },
+  /// Wraps an error raised while resolving `specifier` from `importer`.
+  Resolve {
+    specifier: String,
+    importer: Option<String>,
+    source: Box<DenoLoaderError>,
+  },
+  /// Wraps an error raised while loading the module `id`.
+  Load {
+    id: String,
+    source: Box<DenoLoaderError>,
+  },
+}
+
+impl DenoLoaderError {
+  pub fn in_resolve(self, specifier: &str, importer: Option<&str>) -> Self {
+    Self::Resolve {
+      specifier: specifier.to_string(),
+      importer: importer.map(ToString::to_string),
+      source: Box::new(self),
+    }
+  }
+
+  pub fn in_load(self, id: &str) -> Self {
+    Self::Load { id: id.to_string(), source: Box::new(self) }
+  }
+}
+
+impl fmt::Display for DenoLoaderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::DenoNotFound { executable, source } => {
+        write!(f, "Failed to execute \"{executable}\", is deno installed? ({source})")
+      }
+      Self::DenoInfoFailed { specifier, status, stderr } => {
+        write!(f, "`deno info` failed for \"{specifier}\" ({status})")?;
+        if !stderr.trim().is_empty() {
+          write!(f, ":\n{}", stderr.trim_end())?;
+        }
+        Ok(())
+      }
+      Self::InvalidDenoInfoJson { specifier, source } => {
+        write!(f, "Failed to parse `deno info --json` output for \"{specifier}\": {source}")
+      }
+      Self::ReadGraphFile { path, source } => {
+        write!(f, "Failed to read module graph \"{path}\": {source}")
+      }
+      Self::InvalidGraphFile { path, source } => {
+        write!(f, "Invalid module graph \"{path}\", expected `deno info --json` output: {source}")
+      }
+      Self::CircularRedirect { specifier } => {
+        write!(f, "Circular redirect detected while resolving \"{specifier}\"")
+      }
+      Self::LocalPathNotFound { specifier } => {
+        write!(f, "No local cache path reported by deno for \"{specifier}\"")
+      }
+      Self::ReadCacheFile { path, source } => {
+        write!(f, "Failed to read cached module \"{path}\": {source}")
+      }
+      Self::ReadConfig { path, source } => write!(f, "Failed to read \"{path}\": {source}"),
+      Self::InvalidConfig { path, source } => write!(f, "Invalid deno config \"{path}\": {source}"),
+      Self::InvalidImportMap { url, message } => {
+        write!(f, "Invalid import map \"{url}\": {message}")
+      }
+      Self::InvalidExternalPattern { pattern, source } => {
+        write!(f, "Invalid external pattern \"{pattern}\": {source}")
+      }
+      Self::WasmNotSupported { specifier } => {
+        write!(f, "Bundling the remote wasm module \"{specifier}\" is not supported")
+      }
+      Self::ModuleError { specifier, error, importer_chain } => {
+        write!(f, "deno could not load \"{specifier}\": {error}")?;
+        for importer in importer_chain {
+          write!(f, "\n    imported from {importer}")?;
+        }
+        Ok(())
+      }
+      Self::Resolve { specifier, importer: Some(importer), source } => {
+        write!(f, "Failed to resolve \"{specifier}\" imported from \"{importer}\": {source}")
+      }
+      Self::Resolve { specifier, importer: None, source } => {
+        write!(f, "Failed to resolve \"{specifier}\": {source}")
+      }
+      Self::Load { id, source } => write!(f, "Failed to load \"{id}\": {source}"),
+    }
+  }
+}
+
+impl std::error::Error for DenoLoaderError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::DenoNotFound { source, .. }
+      | Self::ReadCacheFile { source, .. }
+      | Self::ReadGraphFile { source, .. }
+      | Self::ReadConfig { source, .. } => Some(source),
+      Self::InvalidDenoInfoJson { source, .. }
+      | Self::InvalidConfig { source, .. }
+      | Self::InvalidGraphFile { source, .. } => Some(source),
+      Self::InvalidExternalPattern { source, .. } => Some(source),
+      Self::Resolve { source, .. } | Self::Load { source, .. } => Some(source.as_ref()),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,98 @@
+use regex::Regex;
+
+use crate::error::DenoLoaderError;
+
+/// Decides which jsr, npm, http(s) and node specifiers are left as runtime imports.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalPolicy {
+  patterns: Vec<Regex>,
+}
+
+impl ExternalPolicy {
+  /// Compiles `patterns`. A pattern wrapped in slashes like `/^jsr:@std\//` is a regular
+  /// expression, anything else is a glob where `*` matches any run of characters and `?` a single
+  /// one, e.g. `jsr:*` or `https://esm.sh/*`.
+  pub fn new(patterns: &[String]) -> Result<Self, DenoLoaderError> {
+    let patterns = patterns
+      .iter()
+      .map(|pattern| {
+        let source = match pattern.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+          Some(regex) if !regex.is_empty() => regex.to_string(),
+          _ => glob_to_regex(pattern),
+        };
+        Regex::new(&source).map_err(|source| DenoLoaderError::InvalidExternalPattern {
+          pattern: pattern.clone(),
+          source,
+        })
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(Self { patterns })
+  }
+
+  pub fn matches(&self, specifier: &str) -> bool {
+    self.patterns.iter().any(|pattern| pattern.is_match(specifier))
+  }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+  let mut regex = String::from("^");
+  for ch in glob.chars() {
+    match ch {
+      '*' => regex.push_str(".*"),
+      '?' => regex.push('.'),
+      ch => regex.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+    }
+  }
+  regex.push('$');
+  regex
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy(patterns: &[&str]) -> ExternalPolicy {
+    ExternalPolicy::new(&patterns.iter().map(ToString::to_string).collect::<Vec<_>>()).unwrap()
+  }
+
+  #[test]
+  fn globs() {
+    assert_eq!(glob_to_regex("npm:react@1?.*"), r"^npm:react@1.\..*$");
+    assert_eq!(glob_to_regex("jsr:@std/c++"), r"^jsr:@std/c\+\+$");
+
+    let policy = policy(&["jsr:*", "npm:preact@1?.*"]);
+    assert!(policy.matches("jsr:@std/path@1"));
+    assert!(policy.matches("npm:preact@10.24"));
+    // `.` is literal and globs are anchored.
+    assert!(!policy.matches("npm:preact@10x24"));
+    assert!(!policy.matches("npm:preact@1.24"));
+    assert!(!policy.matches("npm:jsr:foo"));
+  }
+
+  #[test]
+  fn regexes() {
+    let policy = policy(&[r"/^npm:react(-dom)?@/"]);
+    assert!(policy.matches("npm:react@18"));
+    assert!(policy.matches("npm:react-dom@18/client"));
+    assert!(!policy.matches("npm:react-is@18"));
+
+    // Unanchored unless the regex says otherwise.
+    assert!(self::policy(&["/esm\\.sh/"]).matches("https://esm.sh/preact"));
+  }
+
+  #[test]
+  fn empty_slashes_are_a_glob() {
+    let policy = policy(&["//"]);
+    assert!(policy.matches("//"));
+    assert!(!policy.matches("npm:preact"));
+  }
+
+  #[test]
+  fn invalid_regex() {
+    let err = ExternalPolicy::new(&["jsr:*".to_string(), "/npm:(/".to_string()]).unwrap_err();
+    let DenoLoaderError::InvalidExternalPattern { pattern, .. } = &err else {
+      panic!("unexpected error: {err}");
+    };
+    assert_eq!(pattern, "/npm:(/");
+  }
+}
@@ -0,0 +1,309 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::deno_dir::{media_type_for, DenoDir};
+use crate::deno_info::{DenoResolveResult, ModuleInfo};
+use crate::lockfile::Lockfile;
+use crate::package_json::{resolve_exports, Exports};
+
+const DEFAULT_JSR_URL: &str = "https://jsr.io/";
+
+/// A parsed `jsr:@scope/name@version/subpath` specifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsrSpecifier {
+  pub scope: String,
+  pub name: String,
+  pub version_req: Option<String>,
+  pub subpath: Option<String>,
+}
+
+impl JsrSpecifier {
+  pub fn parse(specifier: &str) -> Option<Self> {
+    let rest = specifier.strip_prefix("jsr:")?;
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let (scope, rest) = rest.strip_prefix('@')?.split_once('/')?;
+    let name_end = rest.find(['@', '/']).unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_end);
+    let (version_req, subpath) = match rest.strip_prefix('@') {
+      Some(versioned) => match versioned.split_once('/') {
+        Some((version, subpath)) => (Some(version), Some(subpath)),
+        None => (Some(versioned), None),
+      },
+      None => (None, rest.strip_prefix('/')),
+    };
+    if scope.is_empty() || name.is_empty() {
+      return None;
+    }
+
+    Some(Self {
+      scope: scope.to_string(),
+      name: name.to_string(),
+      version_req: version_req.filter(|v| !v.is_empty()).map(ToString::to_string),
+      subpath: subpath.filter(|s| !s.is_empty()).map(ToString::to_string),
+    })
+  }
+
+  /// `@scope/name`
+  pub fn package_name(&self) -> String {
+    format!("@{}/{}", self.scope, self.name)
+  }
+
+  /// The key deno uses in `deno.lock`, e.g. `jsr:@std/path@1`.
+  pub fn package_req(&self) -> String {
+    match &self.version_req {
+      Some(version_req) => format!("jsr:{}@{version_req}", self.package_name()),
+      None => format!("jsr:{}", self.package_name()),
+    }
+  }
+
+  /// The key to look up in the version's `exports`, e.g. `.` or `./from-file-url`.
+  pub fn export_name(&self) -> String {
+    self.subpath.as_ref().map_or_else(|| ".".to_string(), |subpath| format!("./{subpath}"))
+  }
+}
+
+#[derive(Deserialize, Debug)]
+struct PackageMeta {
+  latest: Option<String>,
+  #[serde(default)]
+  versions: HashMap<String, PackageMetaVersion>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PackageMetaVersion {
+  #[serde(default)]
+  yanked: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionMeta {
+  #[serde(default)]
+  exports: Exports,
+}
+
+/// Resolves `jsr:` specifiers from package metadata, without asking deno.
+///
+/// Metadata is read from `$DENO_DIR` for remote registries, or directly from disk when the
+/// registry is a `file:` URL, which is handy for mirrors and offline fixtures.
+#[derive(Debug, Clone)]
+pub struct JsrResolver {
+  pub registry: url::Url,
+  pub deno_dir: Option<DenoDir>,
+  pub lockfile: Option<Lockfile>,
+}
+
+impl JsrResolver {
+  /// `registry` defaults to `JSR_URL` like deno does, then `https://jsr.io/`.
+  pub fn new(
+    registry: Option<&str>,
+    deno_dir: Option<DenoDir>,
+    lockfile: Option<Lockfile>,
+  ) -> Option<Self> {
+    let registry = match registry {
+      Some(registry) => registry.to_string(),
+      None => std::env::var("JSR_URL").unwrap_or_else(|_| DEFAULT_JSR_URL.to_string()),
+    };
+    let registry = if registry.ends_with('/') { registry } else { format!("{registry}/") };
+    Some(Self { registry: url::Url::parse(&registry).ok()?, deno_dir, lockfile })
+  }
+
+  fn read(&self, url: &url::Url) -> Option<Vec<u8>> {
+    if url.scheme() == "file" {
+      std::fs::read(url.to_file_path().ok()?).ok()
+    } else {
+      self.deno_dir.as_ref()?.read(url.as_str())
+    }
+  }
+
+  fn package_url(&self, package_name: &str) -> Option<url::Url> {
+    self.registry.join(&format!("{package_name}/")).ok()
+  }
+
+  pub fn select_version(&self, specifier: &JsrSpecifier) -> Option<String> {
+    if let Some(locked) =
+      self.lockfile.as_ref().and_then(|lockfile| lockfile.pinned_version(&specifier.package_req()))
+    {
+      return Some(locked.to_string());
+    }
+
+    let meta_url = self.package_url(&specifier.package_name())?.join("meta.json").ok()?;
+    let meta: PackageMeta = serde_json::from_slice(&self.read(&meta_url)?).ok()?;
+    let version_req = match specifier.version_req.as_deref() {
+      None | Some("*") | Some("latest") => return meta.latest,
+      Some(version_req) => version_req,
+    };
+    if meta.versions.contains_key(version_req) {
+      return Some(version_req.to_string());
+    }
+
+    let req = parse_version_req(version_req)?;
+    meta
+      .versions
+      .iter()
+      .filter(|(_, info)| !info.yanked)
+      .filter_map(|(version, _)| semver::Version::parse(version).ok())
+      .filter(|version| req.matches(version))
+      .max()
+      .map(|version| version.to_string())
+  }
+
+  /// Maps the specifier to the module url inside the registry, e.g.
+  /// `jsr:@std/path@1/from-file-url` to `https://jsr.io/@std/path/1.0.8/from_file_url.ts`.
+  pub fn resolve_url(&self, specifier: &JsrSpecifier, conditions: &[String]) -> Option<url::Url> {
+    let version = self.select_version(specifier)?;
+    let version_url =
+      self.package_url(&specifier.package_name())?.join(&format!("{version}/")).ok()?;
+    let meta_url =
+      self.package_url(&specifier.package_name())?.join(&format!("{version}_meta.json")).ok()?;
+    let meta: VersionMeta = serde_json::from_slice(&self.read(&meta_url)?).ok()?;
+    let target = resolve_exports(&meta.exports, &specifier.export_name(), conditions)?;
+    version_url.join(target.trim_start_matches("./")).ok()
+  }
+
+  pub fn resolve(&self, specifier: &str, conditions: &[String]) -> Option<DenoResolveResult> {
+    let url = self.resolve_url(&JsrSpecifier::parse(specifier)?, conditions)?;
+    if url.scheme() != "file" {
+      return self.deno_dir.as_ref()?.resolve(url.as_str());
+    }
+
+    let path: PathBuf = url.to_file_path().ok()?;
+    if !path.is_file() {
+      return None;
+    }
+    let local = path.to_string_lossy().into_owned();
+    let media_type = media_type_for(&url, None)?;
+    Some(DenoResolveResult {
+      // Local mirrors resolve to plain paths so rolldown loads them like any other file.
+      redirected: local.clone(),
+      local_path: Some(local.clone()),
+      media_type: Some(media_type.clone()),
+      module: Some(ModuleInfo::esm(url.to_string(), local, media_type)),
+      importer_chain: Vec::new(),
+      has_dependencies: false,
+    })
+  }
+}
+
+/// Converts an npm style range (`1`, `^1.2`, `>=1 <2`) to a `semver::VersionReq`.
+pub(crate) fn parse_version_req(version_req: &str) -> Option<semver::VersionReq> {
+  if version_req.contains("||") {
+    return None;
+  }
+  if let Ok(exact) = semver::Version::parse(version_req) {
+    return Some(semver::VersionReq {
+      comparators: vec![semver::Comparator {
+        op: semver::Op::Exact,
+        major: exact.major,
+        minor: Some(exact.minor),
+        patch: Some(exact.patch),
+        pre: exact.pre,
+      }],
+    });
+  }
+  semver::VersionReq::parse(&version_req.split_whitespace().collect::<Vec<_>>().join(", ")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::deno_info::DenoMediaType;
+  use std::path::Path;
+
+  /// A copy of the jsr.io layout for `@std/path`: 1.0.8 is the latest, 1.1.0 is yanked and 1.0.7
+  /// has no files.
+  fn fixture_resolver(lockfile: Option<Lockfile>) -> JsrResolver {
+    let registry = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/jsr_registry");
+    let registry = url::Url::from_directory_path(registry).unwrap();
+    JsrResolver::new(Some(registry.as_str()), None, lockfile).unwrap()
+  }
+
+  fn jsr(specifier: &str) -> JsrSpecifier {
+    JsrSpecifier::parse(specifier).unwrap()
+  }
+
+  #[test]
+  fn parse_specifiers() {
+    assert_eq!(
+      jsr("jsr:@std/path@^1.0.0/from-file-url"),
+      JsrSpecifier {
+        scope: "std".to_string(),
+        name: "path".to_string(),
+        version_req: Some("^1.0.0".to_string()),
+        subpath: Some("from-file-url".to_string()),
+      }
+    );
+    assert_eq!(jsr("jsr:/@std/path@1").version_req.as_deref(), Some("1"));
+    assert_eq!(jsr("jsr:@std/path/posix").version_req, None);
+    assert_eq!(jsr("jsr:@std/path/posix").subpath.as_deref(), Some("posix"));
+    assert_eq!(jsr("jsr:@std/path@").version_req, None);
+    assert_eq!(jsr("jsr:@std/path").package_req(), "jsr:@std/path");
+    assert_eq!(jsr("jsr:@std/path@1/posix").package_req(), "jsr:@std/path@1");
+    assert_eq!(jsr("jsr:@std/path@1/posix").export_name(), "./posix");
+    assert_eq!(jsr("jsr:@std/path").export_name(), ".");
+
+    assert_eq!(JsrSpecifier::parse("jsr:std/path"), None);
+    assert_eq!(JsrSpecifier::parse("jsr:@std"), None);
+    assert_eq!(JsrSpecifier::parse("npm:@std/path"), None);
+  }
+
+  #[test]
+  fn version_reqs() {
+    let matches = |req: &str, version: &str| {
+      parse_version_req(req).unwrap().matches(&semver::Version::parse(version).unwrap())
+    };
+    assert!(matches("1", "1.9.0"));
+    assert!(!matches("1", "2.0.0"));
+    assert!(matches("^1.2", "1.3.0"));
+    assert!(matches(">=1 <2", "1.5.0"));
+    assert!(!matches(">=1 <2", "2.0.0"));
+    // An exact version only matches itself, unlike semver's default caret.
+    assert!(matches("1.0.7", "1.0.7"));
+    assert!(!matches("1.0.7", "1.0.8"));
+    assert!(parse_version_req("1 || 2").is_none());
+  }
+
+  #[test]
+  fn select_version_from_meta() {
+    let resolver = fixture_resolver(None);
+    let select = |specifier: &str| resolver.select_version(&jsr(specifier));
+    assert_eq!(select("jsr:@std/path").as_deref(), Some("1.0.8"));
+    assert_eq!(select("jsr:@std/path@latest").as_deref(), Some("1.0.8"));
+    // Yanked versions are skipped by ranges but can still be requested exactly.
+    assert_eq!(select("jsr:@std/path@^1").as_deref(), Some("1.0.8"));
+    assert_eq!(select("jsr:@std/path@1.1.0").as_deref(), Some("1.1.0"));
+    assert_eq!(select("jsr:@std/path@~1.0.0 <1.0.8").as_deref(), Some("1.0.7"));
+    assert_eq!(select("jsr:@std/path@0.225").as_deref(), Some("0.225.2"));
+    assert_eq!(select("jsr:@std/path@2"), None);
+    assert_eq!(select("jsr:@std/missing"), None);
+  }
+
+  #[test]
+  fn select_version_prefers_lockfile() {
+    let lockfile = Lockfile {
+      specifiers: HashMap::from([("jsr:@std/path@1".to_string(), "1.0.7".to_string())]),
+    };
+    let resolver = fixture_resolver(Some(lockfile));
+    assert_eq!(resolver.select_version(&jsr("jsr:@std/path@1")).as_deref(), Some("1.0.7"));
+    assert_eq!(resolver.select_version(&jsr("jsr:@std/path@^1")).as_deref(), Some("1.0.8"));
+  }
+
+  #[test]
+  fn resolve_exports_to_local_files() {
+    let resolver = fixture_resolver(None);
+    let version_dir =
+      Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/jsr_registry/@std/path/1.0.8");
+
+    let result = resolver.resolve("jsr:@std/path@1/from-file-url", &[]).unwrap();
+    assert_eq!(Path::new(&result.redirected), version_dir.join("from_file_url.ts"));
+    assert_eq!(result.local_path.as_deref(), Some(result.redirected.as_str()));
+    assert_eq!(result.media_type, Some(DenoMediaType::TypeScript));
+
+    let result = resolver.resolve("jsr:@std/path", &[]).unwrap();
+    assert_eq!(Path::new(&result.redirected), version_dir.join("mod.ts"));
+
+    // Unknown exports and versions without files are left to deno.
+    assert!(resolver.resolve("jsr:@std/path@1/posix", &[]).is_none());
+    assert!(resolver.resolve("jsr:@std/path@1.0.7", &[]).is_none());
+  }
+}
@@ -1,4 +1,33 @@
+mod deno_config;
+mod deno_dir;
+mod deno_info;
 #[allow(clippy::manual_async_fn)]
 mod deno_loader_plugin;
+mod disk_cache;
+mod error;
+mod external;
+mod jsr;
+mod lockfile;
+mod npm;
+mod npm_cache;
+mod options;
+mod package_json;
+mod provider;
+#[cfg(test)]
+mod test_util;
 
+pub use deno_config::{DenoConfig, DenoConfigJson, WorkspaceMember};
+pub use deno_dir::DenoDir;
+pub use deno_info::{DenoInfoJsonV1, DenoMediaType, ModuleInfo, NpmPackageInfo};
 pub use deno_loader_plugin::DenoLoaderPlugin;
+pub use error::DenoLoaderError;
+pub use external::ExternalPolicy;
+pub use jsr::{JsrResolver, JsrSpecifier};
+pub use lockfile::Lockfile;
+pub use npm::NpmSpecifier;
+pub use npm_cache::NpmCache;
+pub use options::{DenoLoaderOptions, ExternalSpecifier, NodeModulesDirMode};
+pub use provider::{
+  DenoInfoFuture, DenoInfoProvider, DenoSubprocessProvider, DenoVersionFuture,
+  StaticDenoInfoProvider,
+};
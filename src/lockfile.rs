@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::npm::NpmSpecifier;
+
+#[derive(Deserialize, Debug, Default)]
+struct LockfileJson {
+  #[serde(default)]
+  specifiers: HashMap<String, String>,
+  #[serde(default)]
+  packages: Option<LockfilePackagesV3>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct LockfilePackagesV3 {
+  #[serde(default)]
+  specifiers: HashMap<String, String>,
+}
+
+/// The parts of `deno.lock` needed to pick the same package versions as deno.
+#[derive(Debug, Default, Clone)]
+pub struct Lockfile {
+  /// Package requirement, e.g. `jsr:@std/path@1`, to the locked version, e.g. `1.0.8`.
+  pub specifiers: HashMap<String, String>,
+}
+
+impl Lockfile {
+  pub fn load(path: &Path) -> Option<Self> {
+    let bytes = std::fs::read(path).ok()?;
+    let json: LockfileJson = serde_json::from_slice(&bytes).ok()?;
+    // v3 lockfiles map to a full specifier (`jsr:@std/path@1.0.8`), v4 only to the version.
+    let specifiers = json
+      .specifiers
+      .into_iter()
+      .chain(json.packages.map(|packages| packages.specifiers).unwrap_or_default())
+      .map(|(req, locked)| {
+        // npm entries may carry peer dependency suffixes, e.g. `18.3.1_react@18.3.1`.
+        let version = if let Some(package_ref) = locked.strip_prefix("npm:") {
+          // Split off the name first, the peer suffix has an `@` of its own.
+          NpmSpecifier::parse_package_ref(package_ref)
+            .and_then(|package| package.exact_version().map(ToString::to_string))
+            .unwrap_or_default()
+        } else if let Some(full) = locked.strip_prefix("jsr:") {
+          full.rsplit_once('@').map_or(full, |(_, version)| version).to_string()
+        } else if let Some(npm) = NpmSpecifier::parse(&req) {
+          // v4 only records the version of the package id.
+          let package = NpmSpecifier { version_req: Some(locked), ..npm };
+          package.exact_version().unwrap_or_default().to_string()
+        } else {
+          locked
+        };
+        (req, version)
+      })
+      .collect();
+    Some(Self { specifiers })
+  }
+
+  pub fn pinned_version(&self, package_req: &str) -> Option<&str> {
+    self.specifiers.get(package_req).map(String::as_str)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::TempDir;
+
+  fn load(contents: &str) -> Lockfile {
+    let tmp = TempDir::new();
+    Lockfile::load(&tmp.write("deno.lock", contents)).unwrap()
+  }
+
+  #[test]
+  fn v3_specifiers() {
+    let lockfile = load(
+      r#"{
+        "version": "3",
+        "packages": {
+          "specifiers": {
+            "jsr:@std/path@1": "jsr:@std/path@1.0.8",
+            "npm:preact@10": "npm:preact@10.24.3",
+            "npm:@emotion/react@11": "npm:@emotion/react@11.11.4_react@18.3.1",
+            "npm:react-dom@18": "npm:react-dom@18.3.1_react@18.3.1"
+          }
+        }
+      }"#,
+    );
+    assert_eq!(lockfile.pinned_version("jsr:@std/path@1"), Some("1.0.8"));
+    assert_eq!(lockfile.pinned_version("npm:preact@10"), Some("10.24.3"));
+    assert_eq!(lockfile.pinned_version("npm:@emotion/react@11"), Some("11.11.4"));
+    assert_eq!(lockfile.pinned_version("npm:react-dom@18"), Some("18.3.1"));
+    assert_eq!(lockfile.pinned_version("npm:react@18"), None);
+  }
+
+  #[test]
+  fn v4_specifiers() {
+    let lockfile = load(
+      r#"{
+        "version": "4",
+        "specifiers": {
+          "jsr:@std/path@1": "1.0.8",
+          "npm:@emotion/react@11": "11.11.4_react@18.3.1"
+        }
+      }"#,
+    );
+    assert_eq!(lockfile.pinned_version("jsr:@std/path@1"), Some("1.0.8"));
+    assert_eq!(lockfile.pinned_version("npm:@emotion/react@11"), Some("11.11.4"));
+  }
+}
@@ -0,0 +1,129 @@
+/// A parsed `npm:@scope/name@version/subpath` specifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpmSpecifier {
+  /// Package name including its scope, e.g. `@types/node`.
+  pub name: String,
+  pub version_req: Option<String>,
+  pub subpath: Option<String>,
+}
+
+impl NpmSpecifier {
+  /// Parses `npm:` specifiers, including the `npm:/name@version` form deno uses for resolved
+  /// packages.
+  pub fn parse(specifier: &str) -> Option<Self> {
+    let rest = specifier.strip_prefix("npm:")?;
+    Self::parse_package_ref(rest.strip_prefix('/').unwrap_or(rest))
+  }
+
+  /// Parses a specifier without the `npm:` prefix, e.g. `preact@10/hooks` or a deno info package id
+  /// like `@types/node@22.10.2`.
+  pub fn parse_package_ref(package_ref: &str) -> Option<Self> {
+    // The name of a scoped package spans two path segments.
+    let name_start = if package_ref.starts_with('@') { package_ref.find('/')? + 1 } else { 0 };
+    let name_end =
+      package_ref[name_start..].find(['@', '/']).map_or(package_ref.len(), |i| name_start + i);
+    let (name, rest) = package_ref.split_at(name_end);
+    let (version_req, subpath) = match rest.strip_prefix('@') {
+      Some(versioned) => match versioned.split_once('/') {
+        Some((version, subpath)) => (Some(version), Some(subpath)),
+        None => (Some(versioned), None),
+      },
+      None => (None, rest.strip_prefix('/')),
+    };
+    if name.is_empty() || name.ends_with('/') {
+      return None;
+    }
+
+    Some(Self {
+      name: name.to_string(),
+      version_req: version_req.filter(|v| !v.is_empty()).map(ToString::to_string),
+      subpath: subpath.filter(|s| !s.is_empty()).map(ToString::to_string),
+    })
+  }
+
+  /// The version without the peer dependency suffix deno adds to package ids, e.g. `18.3.1` for
+  /// `react-dom@18.3.1_react@18.3.1`.
+  pub fn exact_version(&self) -> Option<&str> {
+    let version = self.version_req.as_deref()?;
+    Some(version.split_once('_').map_or(version, |(version, _)| version))
+  }
+
+  /// The bare specifier node resolution understands, e.g. `preact/hooks`.
+  pub fn bare_specifier(&self) -> String {
+    match &self.subpath {
+      Some(subpath) => format!("{}/{subpath}", self.name),
+      None => self.name.clone(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn npm(name: &str, version_req: Option<&str>, subpath: Option<&str>) -> NpmSpecifier {
+    NpmSpecifier {
+      name: name.to_string(),
+      version_req: version_req.map(ToString::to_string),
+      subpath: subpath.map(ToString::to_string),
+    }
+  }
+
+  #[test]
+  fn parse_specifiers() {
+    assert_eq!(NpmSpecifier::parse("npm:@types/node"), Some(npm("@types/node", None, None)));
+    assert_eq!(
+      NpmSpecifier::parse("npm:@types/node/package.json"),
+      Some(npm("@types/node", None, Some("package.json")))
+    );
+    assert_eq!(NpmSpecifier::parse("npm:preact/hooks"), Some(npm("preact", None, Some("hooks"))));
+    assert_eq!(
+      NpmSpecifier::parse("npm:lodash-es@4/debounce"),
+      Some(npm("lodash-es", Some("4"), Some("debounce")))
+    );
+    assert_eq!(
+      NpmSpecifier::parse("npm:@types/node@^22.0.0/fs"),
+      Some(npm("@types/node", Some("^22.0.0"), Some("fs")))
+    );
+    assert_eq!(NpmSpecifier::parse("npm:preact@"), Some(npm("preact", None, None)));
+  }
+
+  #[test]
+  fn parse_resolved_specifiers() {
+    assert_eq!(
+      NpmSpecifier::parse("npm:/preact@10.24.3/hooks"),
+      Some(npm("preact", Some("10.24.3"), Some("hooks")))
+    );
+    assert_eq!(
+      NpmSpecifier::parse("npm:/@types/node@22.10.2"),
+      Some(npm("@types/node", Some("22.10.2"), None))
+    );
+  }
+
+  #[test]
+  fn parse_package_ids() {
+    // Peer dependency suffixes stay part of the version, `exact_version` cuts them.
+    let react_dom = NpmSpecifier::parse_package_ref("react-dom@18.3.1_react@18.3.1").unwrap();
+    assert_eq!(react_dom, npm("react-dom", Some("18.3.1_react@18.3.1"), None));
+    assert_eq!(react_dom.exact_version(), Some("18.3.1"));
+    let emotion = NpmSpecifier::parse_package_ref("@emotion/react@11.11.4_react@18.3.1").unwrap();
+    assert_eq!(emotion, npm("@emotion/react", Some("11.11.4_react@18.3.1"), None));
+    assert_eq!(emotion.exact_version(), Some("11.11.4"));
+    assert_eq!(npm("preact", Some("10.24.3"), None).exact_version(), Some("10.24.3"));
+    assert_eq!(npm("preact", None, None).exact_version(), None);
+  }
+
+  #[test]
+  fn parse_invalid_specifiers() {
+    assert_eq!(NpmSpecifier::parse("jsr:@std/path"), None);
+    assert_eq!(NpmSpecifier::parse("npm:"), None);
+    assert_eq!(NpmSpecifier::parse("npm:@types"), None);
+    assert_eq!(NpmSpecifier::parse("npm:@types/"), None);
+  }
+
+  #[test]
+  fn bare_specifier() {
+    assert_eq!(npm("preact", Some("10"), Some("hooks")).bare_specifier(), "preact/hooks");
+    assert_eq!(npm("@types/node", Some("22"), None).bare_specifier(), "@types/node");
+  }
+}
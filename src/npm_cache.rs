@@ -0,0 +1,64 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::deno_dir::DenoDir;
+use crate::jsr::parse_version_req;
+use crate::options::DenoLoaderOptions;
+
+const DEFAULT_NPM_REGISTRY: &str = "https://registry.npmjs.org/";
+
+/// Deno's global npm cache, `$DENO_DIR/npm/<registry host>/<name>/<version>`.
+#[derive(Debug, Clone)]
+pub struct NpmCache {
+  pub root: PathBuf,
+}
+
+impl NpmCache {
+  /// Uses the registry from `NPM_CONFIG_REGISTRY` like deno does.
+  pub fn new(deno_dir: &DenoDir, options: &DenoLoaderOptions) -> Option<Self> {
+    let registry = options
+      .env
+      .get("NPM_CONFIG_REGISTRY")
+      .cloned()
+      .or_else(|| std::env::var("NPM_CONFIG_REGISTRY").ok())
+      .unwrap_or_else(|| DEFAULT_NPM_REGISTRY.to_string());
+    let registry = url::Url::parse(&registry).ok()?;
+    let host = match registry.port() {
+      Some(port) => format!("{}_{port}", registry.host_str()?),
+      None => registry.host_str()?.to_string(),
+    };
+    Some(Self { root: deno_dir.root.join("npm").join(host) })
+  }
+
+  pub fn package_dir(&self, name: &str, version: &str) -> PathBuf {
+    self.root.join(name).join(version)
+  }
+
+  /// Name, version and directory of the cached package containing `path`.
+  pub fn package_of(&self, path: &Path) -> Option<(String, String, PathBuf)> {
+    let relative = path.strip_prefix(&self.root).ok()?;
+    let mut components = relative.components().filter_map(|c| match c {
+      Component::Normal(part) => part.to_str(),
+      _ => None,
+    });
+    let first = components.next()?;
+    let name = if first.starts_with('@') {
+      format!("{first}/{}", components.next()?)
+    } else {
+      first.to_string()
+    };
+    let version = components.next()?.to_string();
+    let dir = self.package_dir(&name, &version);
+    Some((name, version, dir))
+  }
+
+  /// Highest cached version of `name` matching `version_req`, for packages deno info didn't list.
+  pub fn best_cached_version(&self, name: &str, version_req: Option<&str>) -> Option<String> {
+    let req = version_req.and_then(parse_version_req);
+    std::fs::read_dir(self.root.join(name))
+      .ok()?
+      .filter_map(|entry| semver::Version::parse(entry.ok()?.file_name().to_str()?).ok())
+      .filter(|version| req.as_ref().is_none_or(|req| req.matches(version)))
+      .max()
+      .map(|version| version.to_string())
+  }
+}
@@ -0,0 +1,154 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::deno_config::strip_jsonc;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DenoLoaderOptions {
+  /// Contents of a deno.json or import map file. When unset, the config from `config` or the
+  /// closest deno.json above `cwd` (not rolldown's `cwd`) is used, then an empty import map.
+  pub import_map_string: Option<String>,
+  /// Path to the deno executable. Defaults to `deno` looked up on PATH.
+  pub deno_path: Option<PathBuf>,
+  /// Working directory for spawned deno processes and where deno.json discovery starts. Defaults
+  /// to the current process directory. The config is discovered when the plugin is created,
+  /// before rolldown's `cwd` is known, so set this when the two differ.
+  pub cwd: Option<PathBuf>,
+  /// Environment variables set on spawned deno processes, e.g. `DENO_DIR`.
+  pub env: HashMap<String, String>,
+  /// Extra flags passed to `deno info`, e.g. `--lock`, `--cached-only` or `--node-modules-dir`.
+  pub extra_args: Vec<String>,
+  /// Config file passed to deno as `--config`. When neither this nor `import_map_string` is set,
+  /// the closest deno.json or deno.jsonc above `cwd` is used.
+  pub config: Option<PathBuf>,
+  /// Run `deno info` once per entry in `build_start` and cache every module of the returned graph,
+  /// instead of running it for each remote specifier. Enabled by default.
+  pub prefetch_graph: bool,
+  /// Directory for resolution results persisted across builds. Disabled when unset.
+  pub cache_dir: Option<PathBuf>,
+  /// Serve already cached http(s) modules from `$DENO_DIR` without spawning deno. Enabled by
+  /// default.
+  pub read_deno_dir: bool,
+  /// Resolve `jsr:` specifiers from cached package metadata before asking deno. Enabled by default.
+  pub native_jsr_resolution: bool,
+  /// Registry used for native jsr resolution, a `file:` url serves a local mirror. Defaults to
+  /// `JSR_URL` or `https://jsr.io/`.
+  pub jsr_registry: Option<String>,
+  /// How npm packages are laid out on disk. Defaults to the `nodeModulesDir` of the deno.json
+  /// passed as `import_map_string`, then to what deno picks for the working directory.
+  pub node_modules_dir: Option<NodeModulesDirMode>,
+  /// Conditions used to pick entries of package `exports`, e.g. `["browser", "import"]`. Defaults
+  /// to rolldown's `resolve.conditionNames`, then to the conditions of rolldown's `platform`, and
+  /// to the conditions deno uses for the `neutral` platform.
+  pub conditions: Option<Vec<String>>,
+  /// jsr, npm, http(s) and node specifiers matching one of these patterns are left as runtime
+  /// imports, e.g. `["jsr:*", "/^npm:react(-dom)?@/"]`. Patterns match the specifier after import
+  /// map resolution; see `ExternalPolicy` for the syntax.
+  pub external: Vec<String>,
+  /// What external imports are rewritten to in the output.
+  pub external_specifier: ExternalSpecifier,
+  /// Url of an external `npm:` import in `cdn` mode. `{name}`, `{version}` and `{subpath}` (empty
+  /// or starting with `/`) are replaced. Defaults to `https://esm.sh/{name}@{version}{subpath}`.
+  pub npm_cdn_template: Option<String>,
+  /// Url of an external `jsr:` import in `cdn` mode, with the same placeholders as
+  /// `npm_cdn_template`. Defaults to `https://esm.sh/jsr/{name}@{version}{subpath}`.
+  pub jsr_cdn_template: Option<String>,
+}
+
+/// The specifier written to the output for an external import.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalSpecifier {
+  /// Keep the specifier after import map resolution, e.g. `jsr:@std/path@1`.
+  #[default]
+  Original,
+  /// Use what deno resolved it to, e.g. `https://jsr.io/@std/path/1.0.8/mod.ts` or
+  /// `npm:preact@10.24.3/hooks`.
+  Resolved,
+  /// Rewrite `jsr:` and `npm:` imports to CDN urls with the versions pinned by deno.lock or the
+  /// module graph, for output that runs in browsers. Other specifiers are kept.
+  Cdn,
+}
+
+/// The `nodeModulesDir` setting of deno.json.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeModulesDirMode {
+  /// Packages only live in deno's global cache.
+  None,
+  /// deno materializes packages under `node_modules/.deno`.
+  Auto,
+  /// Packages are installed by another package manager (BYONM).
+  Manual,
+}
+
+impl NodeModulesDirMode {
+  /// Reads `nodeModulesDir` from deno.json or deno.jsonc contents.
+  pub fn from_config_json(config: &str) -> Option<Self> {
+    let config: serde_json::Value = serde_json::from_str(&strip_jsonc(config)).ok()?;
+    Self::from_value(config.get("nodeModulesDir")?)
+  }
+
+  /// Accepts the string modes as well as the boolean form of deno 1.x.
+  pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+    match value {
+      serde_json::Value::Bool(true) => Some(Self::Auto),
+      serde_json::Value::Bool(false) => Some(Self::None),
+      value => serde_json::from_value(value.clone()).ok(),
+    }
+  }
+
+  /// deno uses `manual` when the project has a package.json and `none` otherwise.
+  pub fn default_for(dir: &Path) -> Self {
+    if dir.join("package.json").is_file() {
+      Self::Manual
+    } else {
+      Self::None
+    }
+  }
+}
+
+impl Default for DenoLoaderOptions {
+  fn default() -> Self {
+    Self {
+      import_map_string: None,
+      deno_path: None,
+      cwd: None,
+      env: HashMap::new(),
+      extra_args: Vec::new(),
+      config: None,
+      prefetch_graph: true,
+      cache_dir: None,
+      read_deno_dir: true,
+      native_jsr_resolution: true,
+      jsr_registry: None,
+      node_modules_dir: None,
+      conditions: None,
+      external: Vec::new(),
+      external_specifier: ExternalSpecifier::Original,
+      npm_cdn_template: None,
+      jsr_cdn_template: None,
+    }
+  }
+}
+
+impl DenoLoaderOptions {
+  pub fn npm_cdn_template(&self) -> &str {
+    self.npm_cdn_template.as_deref().unwrap_or("https://esm.sh/{name}@{version}{subpath}")
+  }
+
+  pub fn jsr_cdn_template(&self) -> &str {
+    self.jsr_cdn_template.as_deref().unwrap_or("https://esm.sh/jsr/{name}@{version}{subpath}")
+  }
+
+  pub fn deno_executable(&self) -> PathBuf {
+    self.deno_path.clone().unwrap_or_else(|| PathBuf::from("deno"))
+  }
+
+  /// Directory deno runs in, used to locate `deno.lock`.
+  pub fn working_dir(&self) -> PathBuf {
+    self.cwd.clone().or_else(|| std::env::current_dir().ok()).unwrap_or_default()
+  }
+}
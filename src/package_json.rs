@@ -0,0 +1,300 @@
+use rolldown_common::Platform;
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Conditions deno itself uses when resolving npm package exports.
+pub const DEFAULT_CONDITIONS: &[&str] = &["deno", "node", "import", "default"];
+
+/// Conditions rolldown uses for `platform` when `resolve.conditionNames` is unset. A neutral build
+/// has none of its own, so it gets deno's.
+pub fn platform_conditions(platform: Platform) -> Vec<String> {
+  let conditions: &[&str] = match platform {
+    Platform::Browser => &["browser", "import", "default"],
+    Platform::Node => &["node", "import", "default"],
+    Platform::Neutral => DEFAULT_CONDITIONS,
+  };
+  conditions.iter().map(ToString::to_string).collect()
+}
+
+const EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "json"];
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackageJson {
+  pub name: Option<String>,
+  pub version: Option<String>,
+  pub main: Option<String>,
+  pub exports: Option<Exports>,
+  pub dependencies: HashMap<String, String>,
+  pub peer_dependencies: HashMap<String, String>,
+  pub optional_dependencies: HashMap<String, String>,
+}
+
+impl PackageJson {
+  pub fn load(package_dir: &Path) -> Option<Self> {
+    let bytes = std::fs::read(package_dir.join("package.json")).ok()?;
+    serde_json::from_slice(&bytes).ok()
+  }
+
+  /// Version requirement this package declares for `name` in any of its dependency fields.
+  pub fn dependency_req(&self, name: &str) -> Option<&str> {
+    self
+      .dependencies
+      .get(name)
+      .or_else(|| self.peer_dependencies.get(name))
+      .or_else(|| self.optional_dependencies.get(name))
+      .map(String::as_str)
+  }
+
+  /// Resolves `subpath` (`None` for the package root) to a file inside `package_dir`, through
+  /// `exports` when present and `main` or plain file lookup otherwise.
+  pub fn resolve(
+    &self,
+    package_dir: &Path,
+    subpath: Option<&str>,
+    conditions: &[String],
+  ) -> Option<PathBuf> {
+    let export_name = subpath.map_or_else(|| ".".to_string(), |subpath| format!("./{subpath}"));
+    if let Some(exports) = &self.exports {
+      let target = resolve_exports(exports, &export_name, conditions)?;
+      let path = package_dir.join(target.trim_start_matches("./"));
+      return path.is_file().then_some(path);
+    }
+
+    let target = match subpath {
+      Some(subpath) => subpath.to_string(),
+      None => self.main.clone().unwrap_or_else(|| "index.js".to_string()),
+    };
+    probe_file(&package_dir.join(target.trim_start_matches("./")))
+  }
+}
+
+/// An `exports` field. Objects keep their key order, which decides the condition that wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Exports {
+  Target(String),
+  /// Targets tried in order until one resolves.
+  Fallbacks(Vec<Exports>),
+  /// Subpaths or conditions, in the order they were written.
+  Map(Vec<(String, Exports)>),
+  /// `null` or any other value that excludes the entry.
+  #[default]
+  Excluded,
+}
+
+impl<'de> Deserialize<'de> for Exports {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ExportsVisitor)
+  }
+}
+
+struct ExportsVisitor;
+
+impl<'de> Visitor<'de> for ExportsVisitor {
+  type Value = Exports;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a package exports field")
+  }
+
+  fn visit_str<E>(self, target: &str) -> Result<Exports, E> {
+    Ok(Exports::Target(target.to_string()))
+  }
+
+  fn visit_bool<E>(self, _: bool) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_i64<E>(self, _: i64) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_u64<E>(self, _: u64) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_f64<E>(self, _: f64) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_unit<E>(self) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_none<E>(self) -> Result<Exports, E> {
+    Ok(Exports::Excluded)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Exports, A::Error> {
+    let mut targets = Vec::new();
+    while let Some(target) = seq.next_element()? {
+      targets.push(target);
+    }
+    Ok(Exports::Fallbacks(targets))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Exports, A::Error> {
+    let mut entries = Vec::new();
+    while let Some(entry) = map.next_entry()? {
+      entries.push(entry);
+    }
+    Ok(Exports::Map(entries))
+  }
+}
+
+impl Exports {
+  fn get(&self, key: &str) -> Option<&Exports> {
+    let Self::Map(entries) = self else { return None };
+    entries.iter().find_map(|(name, target)| (name == key).then_some(target))
+  }
+}
+
+/// Resolves an `exports` field for `export_name` (`.` or `./sub/path`), following node's algorithm
+/// for subpath patterns and nested conditions.
+pub fn resolve_exports(
+  exports: &Exports,
+  export_name: &str,
+  conditions: &[String],
+) -> Option<String> {
+  let map = match exports {
+    Exports::Map(entries) if entries.iter().any(|(key, _)| key.starts_with('.')) => entries,
+    _ => {
+      return if export_name == "." { resolve_target(exports, None, conditions) } else { None };
+    }
+  };
+
+  if let Some(target) = exports.get(export_name) {
+    return resolve_target(target, None, conditions);
+  }
+
+  // The pattern with the longest prefix before `*` wins.
+  map
+    .iter()
+    .filter_map(|(key, target)| {
+      let (prefix, suffix) = key.split_once('*')?;
+      let matched = export_name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+      Some((prefix.len(), matched, target))
+    })
+    .max_by_key(|(prefix_len, ..)| *prefix_len)
+    .and_then(|(_, matched, target)| resolve_target(target, Some(matched), conditions))
+}
+
+fn resolve_target(
+  target: &Exports,
+  pattern_match: Option<&str>,
+  conditions: &[String],
+) -> Option<String> {
+  match target {
+    Exports::Target(target) => Some(match pattern_match {
+      Some(matched) => target.replace('*', matched),
+      None => target.clone(),
+    }),
+    Exports::Fallbacks(targets) => {
+      targets.iter().find_map(|target| resolve_target(target, pattern_match, conditions))
+    }
+    Exports::Map(entries) => entries
+      .iter()
+      .filter(|(condition, _)| condition == "default" || conditions.contains(condition))
+      .find_map(|(_, target)| resolve_target(target, pattern_match, conditions)),
+    Exports::Excluded => None,
+  }
+}
+
+/// Tries `path` as a file, with the usual extensions, and as a directory with an index file.
+pub fn probe_file(path: &Path) -> Option<PathBuf> {
+  if path.is_file() {
+    return Some(path.to_path_buf());
+  }
+  let with_extension = EXTENSIONS.iter().map(|ext| {
+    let mut file = path.as_os_str().to_owned();
+    file.push(format!(".{ext}"));
+    PathBuf::from(file)
+  });
+  let index = EXTENSIONS.iter().map(|ext| path.join(format!("index.{ext}")));
+  with_extension.chain(index).find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::TempDir;
+
+  fn exports(json: &str) -> Exports {
+    serde_json::from_str(json).unwrap()
+  }
+
+  fn conditions(conditions: &[&str]) -> Vec<String> {
+    conditions.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn conditions_follow_key_order() {
+    let exports = exports(
+      r#"{ ".": { "require": "./index.cjs", "import": "./index.mjs", "default": "./index.js" } }"#,
+    );
+    let resolve = |names: &[&str]| resolve_exports(&exports, ".", &conditions(names));
+    assert_eq!(resolve(&["import"]).as_deref(), Some("./index.mjs"));
+    assert_eq!(resolve(&["require"]).as_deref(), Some("./index.cjs"));
+    assert_eq!(resolve(&["browser"]).as_deref(), Some("./index.js"));
+    // The first matching key wins, not the first matching condition.
+    assert_eq!(resolve(&["import", "require"]).as_deref(), Some("./index.cjs"));
+
+    let default_first = self::exports(r#"{ "default": "./index.js", "import": "./index.mjs" }"#);
+    assert_eq!(
+      resolve_exports(&default_first, ".", &conditions(&["import"])).as_deref(),
+      Some("./index.js")
+    );
+  }
+
+  #[test]
+  fn subpath_patterns() {
+    let exports = exports(
+      r#"{
+        "./*": "./dist/*.js",
+        "./features/*": { "browser": "./browser/*.js", "default": "./node/*.js" },
+        "./internal/*": null
+      }"#,
+    );
+    let browser = conditions(&["browser"]);
+    assert_eq!(resolve_exports(&exports, "./debounce", &[]).as_deref(), Some("./dist/debounce.js"));
+    assert_eq!(
+      resolve_exports(&exports, "./features/a", &browser).as_deref(),
+      Some("./browser/a.js")
+    );
+    assert_eq!(resolve_exports(&exports, "./features/a", &[]).as_deref(), Some("./node/a.js"));
+    assert_eq!(resolve_exports(&exports, ".", &[]), None);
+    assert_eq!(resolve_exports(&exports, "./internal/a", &[]), None);
+  }
+
+  #[test]
+  fn platform_conditions_pick_exports() {
+    let tmp = TempDir::new();
+    tmp.write(
+      "package.json",
+      r#"{
+        "name": "pkg",
+        "exports": {
+          ".": { "node": "./node.js", "browser": "./browser.js", "default": "./index.js" },
+          "./worker": { "deno": "./worker.deno.js", "default": "./worker.js" }
+        }
+      }"#,
+    );
+    for file in ["node.js", "browser.js", "index.js", "worker.deno.js", "worker.js"] {
+      tmp.write(file, "");
+    }
+    let package = PackageJson::load(tmp.path()).unwrap();
+    let resolve = |platform: Platform, subpath: Option<&str>| {
+      let path = package.resolve(tmp.path(), subpath, &platform_conditions(platform)).unwrap();
+      path.file_name().unwrap().to_string_lossy().into_owned()
+    };
+
+    assert_eq!(resolve(Platform::Browser, None), "browser.js");
+    assert_eq!(resolve(Platform::Node, None), "node.js");
+    assert_eq!(resolve(Platform::Neutral, None), "node.js");
+    assert_eq!(resolve(Platform::Browser, Some("worker")), "worker.js");
+    assert_eq!(resolve(Platform::Neutral, Some("worker")), "worker.deno.js");
+  }
+}
@@ -0,0 +1,141 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use rolldown_utils::xxhash::xxhash_base64_url;
+
+use crate::deno_info::DenoInfoJsonV1;
+use crate::error::DenoLoaderError;
+use crate::options::DenoLoaderOptions;
+
+/// Source of the module graph used by `DenoLoaderPlugin`.
+///
+/// The default implementation shells out to `deno info --json`. Other implementations can serve a
+/// graph that was generated ahead of time, which also allows using the plugin without deno
+/// installed.
+pub trait DenoInfoProvider: Debug + Send + Sync {
+  fn info<'a>(&'a self, specifier: &'a str) -> DenoInfoFuture<'a>;
+
+  /// Identifies the graph source in the key of the disk cache, so results are only reused while it
+  /// stays the same.
+  fn version(&self) -> DenoVersionFuture<'_>;
+}
+
+pub type DenoInfoFuture<'a> =
+  Pin<Box<dyn Future<Output = Result<DenoInfoJsonV1, DenoLoaderError>> + Send + 'a>>;
+
+pub type DenoVersionFuture<'a> =
+  Pin<Box<dyn Future<Output = Result<String, DenoLoaderError>> + Send + 'a>>;
+
+/// Runs `deno info --json <specifier>` for every lookup.
+///
+/// The process is spawned on the tokio runtime and killed if the returned future is dropped, e.g.
+/// when the build is aborted.
+#[derive(Debug, Default)]
+pub struct DenoSubprocessProvider {
+  pub options: DenoLoaderOptions,
+}
+
+impl DenoSubprocessProvider {
+  pub fn new(options: DenoLoaderOptions) -> Self {
+    Self { options }
+  }
+
+  fn command(&self) -> (PathBuf, tokio::process::Command) {
+    let executable = self.options.deno_executable();
+    let mut command = tokio::process::Command::new(&executable);
+    command.envs(&self.options.env).kill_on_drop(true);
+    if let Some(cwd) = &self.options.cwd {
+      command.current_dir(cwd);
+    }
+    (executable, command)
+  }
+}
+
+impl DenoInfoProvider for DenoSubprocessProvider {
+  fn info<'a>(&'a self, specifier: &'a str) -> DenoInfoFuture<'a> {
+    Box::pin(async move {
+      let (executable, mut command) = self.command();
+      command.args(["info", "--json"]);
+      if let Some(config) = &self.options.config {
+        command.arg("--config").arg(config);
+      }
+      command.args(&self.options.extra_args).arg(specifier);
+
+      let output = command.output().await.map_err(|source| DenoLoaderError::DenoNotFound {
+        executable: executable.display().to_string(),
+        source,
+      })?;
+
+      if !output.status.success() {
+        return Err(DenoLoaderError::DenoInfoFailed {
+          specifier: specifier.to_string(),
+          status: output.status,
+          stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+      }
+
+      serde_json::from_slice(&output.stdout).map_err(|source| {
+        DenoLoaderError::InvalidDenoInfoJson { specifier: specifier.to_string(), source }
+      })
+    })
+  }
+
+  /// First line of `deno --version`, e.g. `deno 2.1.4 (stable, release, x86_64-unknown-linux-gnu)`.
+  fn version(&self) -> DenoVersionFuture<'_> {
+    Box::pin(async move {
+      let (executable, mut command) = self.command();
+      let output = command.arg("--version").output().await.map_err(|source| {
+        DenoLoaderError::DenoNotFound { executable: executable.display().to_string(), source }
+      })?;
+
+      if !output.status.success() {
+        return Err(DenoLoaderError::DenoInfoFailed {
+          specifier: "--version".to_string(),
+          status: output.status,
+          stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+      }
+
+      Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string())
+    })
+  }
+}
+
+/// Serves every lookup from one fixed graph, e.g. a fixture or a graph precomputed in CI.
+#[derive(Debug, Clone)]
+pub struct StaticDenoInfoProvider {
+  pub info: DenoInfoJsonV1,
+}
+
+impl StaticDenoInfoProvider {
+  pub fn new(info: DenoInfoJsonV1) -> Self {
+    Self { info }
+  }
+
+  /// Loads a graph written by `deno info --json <entry> > graph.json`.
+  pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, DenoLoaderError> {
+    let path = path.into();
+    let display = path.display().to_string();
+    let bytes = std::fs::read(&path)
+      .map_err(|source| DenoLoaderError::ReadGraphFile { path: display.clone(), source })?;
+    let info = serde_json::from_slice(&bytes)
+      .map_err(|source| DenoLoaderError::InvalidGraphFile { path: display, source })?;
+    Ok(Self { info })
+  }
+}
+
+impl DenoInfoProvider for StaticDenoInfoProvider {
+  fn info<'a>(&'a self, _specifier: &'a str) -> DenoInfoFuture<'a> {
+    Box::pin(async move { Ok(self.info.clone()) })
+  }
+
+  /// A hash of the roots and modules, so editing the graph invalidates the disk cache.
+  fn version(&self) -> DenoVersionFuture<'_> {
+    Box::pin(async move {
+      let graph = serde_json::to_vec(&(&self.info.roots, &self.info.modules)).unwrap_or_default();
+      Ok(format!("static {}", xxhash_base64_url(&graph)))
+    })
+  }
+}
@@ -0,0 +1 @@
+export const version = "0.225.2";
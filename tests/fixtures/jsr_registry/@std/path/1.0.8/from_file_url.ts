@@ -0,0 +1 @@
+export function fromFileUrl(url: string | URL): string { return new URL(url).pathname; }
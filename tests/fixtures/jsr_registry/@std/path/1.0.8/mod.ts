@@ -0,0 +1 @@
+export * from "./from_file_url.ts";